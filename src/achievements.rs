@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{attract, campaign, hud, player, save, score, ui, game_controller::{GameButton, MenuInput}};
+
+static ACHIEVEMENTS_FILE:&str = "achievements.ron";
+static ACHIEVEMENTS_VERSION:u32 = 1;
+
+pub enum Goal {
+    ClearStageWithoutHit(&'static str),
+    TotalKills(u64),
+    ReachMultiplier(u32),
+}
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub goal: Goal,
+}
+
+pub static ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "untouchable_1",
+        name: "UNTOUCHABLE",
+        description: "Clear stage 1 without being hit",
+        goal: Goal::ClearStageWithoutHit("stage_1"),
+    },
+    Achievement {
+        id: "untouchable_2",
+        name: "STILL UNTOUCHABLE",
+        description: "Clear stage 2 without being hit",
+        goal: Goal::ClearStageWithoutHit("stage_2"),
+    },
+    Achievement {
+        id: "kills_100",
+        name: "WARMING UP",
+        description: "100 kills total",
+        goal: Goal::TotalKills(100),
+    },
+    Achievement {
+        id: "kills_1000",
+        name: "PEWPEWPEW",
+        description: "1000 kills total",
+        goal: Goal::TotalKills(1000),
+    },
+    Achievement {
+        id: "combo_8",
+        name: "CHAIN",
+        description: "Reach a x8 combo",
+        goal: Goal::ReachMultiplier(8),
+    },
+    Achievement {
+        id: "combo_16",
+        name: "UNBROKEN",
+        description: "Reach a x16 combo",
+        goal: Goal::ReachMultiplier(16),
+    },
+];
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct AchievementProgress {
+    pub total_kills: u64,
+    pub unlocked: Vec<String>,
+}
+
+impl AchievementProgress {
+    pub fn load() -> Self {
+        save::load(ACHIEVEMENTS_FILE, ACHIEVEMENTS_VERSION).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        save::save(ACHIEVEMENTS_FILE, ACHIEVEMENTS_VERSION, self);
+    }
+
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked.iter().any(|id| id == achievement.id)
+    }
+}
+
+pub struct AchievementUnlockedEvent {
+    pub name: &'static str,
+}
+
+// whether anyone has been hit since the stage started
+#[derive(Default)]
+struct StageHits(bool);
+
+pub struct AchievementsPlugin;
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<AchievementUnlockedEvent>()
+           .insert_resource(AchievementProgress::load())
+           .init_resource::<StageHits>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(reset_stage_hits.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(save_progress.system())
+           )
+           // these listen for events that can arrive as the game leaves InGame, so they always run
+           .add_system(track_hits.system())
+           .add_system(track_kills.system())
+           .add_system(track_multiplier.system())
+           .add_system(track_stage_clears.system())
+
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Achievements)
+                   .with_system(setup_achievements_list.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Achievements)
+                   .with_system(achievements_list.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Achievements)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+fn unlock(
+    achievement: &Achievement,
+    progress: &mut AchievementProgress,
+    unlocked_event_writer: &mut EventWriter<AchievementUnlockedEvent>,
+) {
+    if progress.is_unlocked(achievement) {
+        return;
+    }
+
+    progress.unlocked.push(achievement.id.to_string());
+    progress.save();
+    unlocked_event_writer.send(AchievementUnlockedEvent { name: achievement.name });
+}
+
+fn reset_stage_hits(
+    mut stage_hits: ResMut<StageHits>,
+) {
+    stage_hits.0 = false;
+}
+
+fn save_progress(
+    progress: Res<AchievementProgress>,
+) {
+    progress.save();
+}
+
+fn track_hits(
+    mut stage_hits: ResMut<StageHits>,
+    mut player_hit_event_reader: EventReader<player::PlayerHitEvent>,
+) {
+    if player_hit_event_reader.iter().next().is_some() {
+        stage_hits.0 = true;
+    }
+}
+
+fn track_kills(
+    attract: Res<attract::AttractMode>,
+    mut progress: ResMut<AchievementProgress>,
+    mut score_event_reader: EventReader<score::ScoreEvent>,
+    mut unlocked_event_writer: EventWriter<AchievementUnlockedEvent>,
+) {
+    let kills = score_event_reader.iter()
+                                  .filter(|event| matches!(event.kind, score::ScoreKind::Kill(_)))
+                                  .count() as u64;
+    if kills == 0 || attract.active {
+        return;
+    }
+
+    progress.total_kills += kills;
+    for achievement in ACHIEVEMENTS.iter() {
+        if let Goal::TotalKills(goal) = achievement.goal {
+            if progress.total_kills >= goal {
+                unlock(achievement, &mut progress, &mut unlocked_event_writer);
+            }
+        }
+    }
+}
+
+fn track_multiplier(
+    score: Res<score::Score>,
+    attract: Res<attract::AttractMode>,
+    mut progress: ResMut<AchievementProgress>,
+    mut unlocked_event_writer: EventWriter<AchievementUnlockedEvent>,
+) {
+    if !score.is_changed() || attract.active {
+        return;
+    }
+
+    let best = score.players.values().map(|player_score| player_score.multiplier).max().unwrap_or(1);
+    for achievement in ACHIEVEMENTS.iter() {
+        if let Goal::ReachMultiplier(goal) = achievement.goal {
+            if best >= goal {
+                unlock(achievement, &mut progress, &mut unlocked_event_writer);
+            }
+        }
+    }
+}
+
+fn track_stage_clears(
+    stage_hits: Res<StageHits>,
+    mut progress: ResMut<AchievementProgress>,
+    mut stage_cleared_event_reader: EventReader<campaign::StageClearedEvent>,
+    mut unlocked_event_writer: EventWriter<AchievementUnlockedEvent>,
+) {
+    for event in stage_cleared_event_reader.iter() {
+        for achievement in ACHIEVEMENTS.iter() {
+            if let Goal::ClearStageWithoutHit(stage) = achievement.goal {
+                if stage == event.stage && !stage_hits.0 {
+                    unlock(achievement, &mut progress, &mut unlocked_event_writer);
+                }
+            }
+        }
+    }
+}
+
+fn setup_achievements_list(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 32.0);
+}
+
+fn achievements_list(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    progress: Res<AchievementProgress>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    let mut value = "ACHIEVEMENTS\n\n".to_string();
+    for achievement in ACHIEVEMENTS.iter() {
+        let mark = if progress.is_unlocked(achievement) { "[X]" } else { "[ ]" };
+        value.push_str(&format!("{} {} - {}", mark, achievement.name, achievement.description));
+        if let Goal::TotalKills(goal) = achievement.goal {
+            value.push_str(&format!(" ({}/{})", progress.total_kills.min(goal), goal));
+        }
+        value.push('\n');
+    }
+    ui::set_screen_text(&mut texts, &value);
+
+    if menu_input.pressed(GameButton::Action) || menu_input.pressed(GameButton::Start) {
+        menu_input.consume();
+        state.set(crate::AppState::LevelSelect).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+
+use crate::{attract, enemy, game_controller, player, settings,
+            camera::MainCamera, input_map::{self, InputMap}};
+
+// how far ahead of the ship the aiming plane is
+static AIM_DISTANCE:f32 = 40.0;
+// how far across the plane a fully pushed right stick reaches
+static STICK_REACH:f32 = 20.0;
+// enemies this close to the aim line pull the shot onto them when aim assist is on
+static ASSIST_DEGREES:f32 = 6.0;
+
+// where the player is aiming this frame, the fixed update samples it into the tick input
+#[derive(Default)]
+pub struct Aim {
+    // None fires straight ahead
+    pub direction: Option<Vec3>,
+    target: Vec3,
+    // whichever of the mouse and right stick moved last is the one aiming
+    using_stick: bool,
+}
+
+struct Reticle;
+
+pub struct AimPlugin;
+impl Plugin for AimPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Aim>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(spawn_reticle.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(update_aim.system().label("update_aim"))
+                   .with_system(update_reticle.system().after("update_aim"))
+           );
+    }
+}
+
+fn spawn_reticle(
+    mut commands: Commands,
+    game_meshes: Res<crate::field::GameMeshes>,
+    mut aim: ResMut<Aim>,
+) {
+    *aim = Aim::default();
+
+    commands.spawn_bundle(PbrBundle {
+                mesh: game_meshes.reticle.clone(),
+                material: game_meshes.reticle_material.clone(),
+                // stood up to face the camera down the x axis
+                transform: Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .insert(Reticle)
+            .insert(crate::field::LevelEntity);
+}
+
+// where a ray from the camera through the cursor crosses the aiming plane
+fn cursor_target(
+    windows: &Windows,
+    cameras: &Query<(&GlobalTransform, &PerspectiveProjection), With<MainCamera>>,
+    plane_x: f32,
+) -> Option<Vec3> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let (camera, projection) = cameras.iter().next()?;
+
+    let ndc = cursor / Vec2::new(window.width(), window.height()) * 2.0 - Vec2::ONE;
+    let half_height = (projection.fov / 2.0).tan();
+    let ray = camera.rotation * Vec3::new(ndc.x * half_height * projection.aspect_ratio, ndc.y * half_height, -1.0);
+    if ray.x <= f32::EPSILON {
+        return None;
+    }
+
+    let distance = (plane_x - camera.translation.x) / ray.x;
+    Some(camera.translation + ray * distance)
+}
+
+fn update_aim(
+    mut aim: ResMut<Aim>,
+    settings: Res<settings::Settings>,
+    attract: Res<attract::AttractMode>,
+    input_map: Res<InputMap>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad: Option<Res<game_controller::GameController>>,
+    windows: Res<Windows>,
+    mut cursor_moved_event_reader: EventReader<CursorMoved>,
+    cameras: Query<(&GlobalTransform, &PerspectiveProjection), With<MainCamera>>,
+    players: Query<&Transform, With<player::Player>>,
+    enemies: Query<&Transform, With<enemy::Enemy>>,
+) {
+    let ship = match players.iter().next() {
+        Some(ship) => ship.translation,
+        None => return,
+    };
+
+    if !settings.aim_mode || attract.active {
+        aim.direction = None;
+        return;
+    }
+
+    let stick = gamepad.map(|gamepad| {
+                           let values = input_map::axis_values(&axes, gamepad.0, &input_map.stick_settings);
+                           Vec2::new(values[&GamepadAxisType::RightStickX], values[&GamepadAxisType::RightStickY])
+                       })
+                       .unwrap_or(Vec2::ZERO);
+    if stick != Vec2::ZERO {
+        aim.using_stick = true;
+    }
+    if cursor_moved_event_reader.iter().next().is_some() {
+        aim.using_stick = false;
+    }
+
+    let plane_x = ship.x + AIM_DISTANCE;
+    let straight_ahead = Vec3::new(plane_x, ship.y, ship.z);
+    aim.target =
+        if aim.using_stick {
+            // screen right is +z from behind the ship
+            straight_ahead + Vec3::new(0.0, stick.y, stick.x) * STICK_REACH
+        } else {
+            cursor_target(&windows, &cameras, plane_x).unwrap_or(straight_ahead)
+        };
+
+    let direction = (aim.target - ship).normalize();
+    aim.direction = Some(direction);
+
+    if !settings.aim_assist {
+        return;
+    }
+
+    // the enemy ahead closest to the aim line, if it's inside the cone
+    let assisted = enemies.iter()
+                          .map(|enemy| enemy.translation)
+                          .filter(|enemy| enemy.x > ship.x)
+                          .map(|enemy| (enemy, (enemy - ship).normalize().angle_between(direction).to_degrees()))
+                          .filter(|(_, angle)| *angle < ASSIST_DEGREES)
+                          .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    if let Some((enemy, _)) = assisted {
+        aim.target = enemy;
+        aim.direction = Some((enemy - ship).normalize());
+    }
+}
+
+fn update_reticle(
+    aim: Res<Aim>,
+    mut reticles: Query<(&mut Transform, &mut Visible), With<Reticle>>,
+) {
+    for (mut transform, mut visible) in reticles.iter_mut() {
+        visible.is_visible = aim.direction.is_some();
+        transform.translation = aim.target;
+    }
+}
@@ -1,33 +1,305 @@
 use bevy::{prelude::*,};
+use bevy::app::AppExit;
+use bevy::asset::{Asset, FileAssetIo, LoadState};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{campaign, hud, sound, ui, game_controller::{GameButton, MenuInput}};
+
+static MANIFEST_FILE:&str = "manifest.ron";
+static LOADING_TIMEOUT_SECONDS:f32 = 20.0;
+static ERROR_ITEMS: [&str; 2] = ["RETRY", "QUIT"];
 
 #[derive(Default)]
 pub struct AssetsLoading {
     pub asset_handles: Vec<HandleUntyped>
 }
-pub fn check_assets_ready(
+
+// asset path and why it didn't load
+#[derive(Default)]
+pub struct AssetErrors {
+    pub failed: Vec<(String, String)>,
+    pub manifest: Option<String>,
+}
+
+// assets/manifest.ron, each section maps a key the code uses to a path under assets/
+#[derive(Default, Deserialize)]
+struct AssetManifest {
+    #[serde(default)]
+    shaders: HashMap<String, String>,
+    #[serde(default)]
+    fonts: HashMap<String, String>,
+    #[serde(default)]
+    models: HashMap<String, String>,
+    #[serde(default)]
+    sounds: HashMap<String, String>,
+    #[serde(default)]
+    levels: HashMap<String, String>,
+}
+
+#[derive(Default)]
+pub struct GameAssets {
+    pub shaders: HashMap<String, Handle<Shader>>,
+    pub fonts: HashMap<String, Handle<Font>>,
+    pub models: HashMap<String, Handle<Mesh>>,
+    pub sounds: HashMap<String, Handle<sound::Tune>>,
+    pub levels: HashMap<String, Handle<campaign::Stage>>,
+}
+
+impl GameAssets {
+    pub fn shader(&self, key: &str) -> Handle<Shader> {
+        get_handle(&self.shaders, "shader", key)
+    }
+
+    pub fn font(&self, key: &str) -> Handle<Font> {
+        get_handle(&self.fonts, "font", key)
+    }
+
+    pub fn model(&self, key: &str) -> Handle<Mesh> {
+        get_handle(&self.models, "model", key)
+    }
+
+    pub fn sound(&self, key: &str) -> Handle<sound::Tune> {
+        get_handle(&self.sounds, "sound", key)
+    }
+
+    fn untyped_handles(&self) -> Vec<HandleUntyped> {
+        fn untyped<T: Asset>(handles: &HashMap<String, Handle<T>>) -> impl Iterator<Item = HandleUntyped> + '_ {
+            handles.values().map(|handle| handle.clone_untyped())
+        }
+
+        untyped(&self.shaders)
+            .chain(untyped(&self.fonts))
+            .chain(untyped(&self.models))
+            .chain(untyped(&self.sounds))
+            .chain(untyped(&self.levels))
+            .collect()
+    }
+}
+
+// a missing key is a bug in the manifest, so complain and hand back an empty handle
+fn get_handle<T: Asset>(handles: &HashMap<String, Handle<T>>, kind: &str, key: &str) -> Handle<T> {
+    handles.get(key).cloned().unwrap_or_else(|| {
+        println!("No {} named {} in {}", kind, key, MANIFEST_FILE);
+        Handle::default()
+    })
+}
+
+fn load_handles<T: Asset>(server: &AssetServer, paths: &HashMap<String, String>) -> HashMap<String, Handle<T>> {
+    paths.iter()
+         .map(|(key, path)| (key.clone(), server.load(path.as_str())))
+         .collect()
+}
+
+struct LoadingTimer(Timer);
+
+#[derive(Default)]
+struct AssetErrorSelection(usize);
+
+// AssetError is pushed over Loading so a retry can pop back without
+// running everything that loads on entering Loading a second time
+pub struct AssetLoaderPlugin;
+impl Plugin for AssetLoaderPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AssetsLoading>()
+           .init_resource::<AssetErrors>()
+           .init_resource::<GameAssets>()
+           .init_resource::<AssetErrorSelection>()
+           .insert_resource(LoadingTimer(Timer::from_seconds(LOADING_TIMEOUT_SECONDS, false)))
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Loading)
+                   .with_system(load_manifest.system().label("load_manifest"))
+                   .with_system(setup_loading.system().after("load_ui_font"))
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Loading)
+                   .with_system(check_assets_ready.system())
+           )
+           .add_system_set(
+               SystemSet::on_pause(crate::AppState::Loading)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           .add_system_set(
+               SystemSet::on_resume(crate::AppState::Loading)
+                   .with_system(setup_loading.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Loading)
+                   .with_system(ui::cleanup_screen.system())
+           )
+
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::AssetError)
+                   .with_system(setup_asset_error.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::AssetError)
+                   .with_system(asset_error_menu.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::AssetError)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+// everything else that loads on entering Loading runs after this and takes its handles from GameAssets
+fn load_manifest(
+    server: Res<AssetServer>,
+    mut game_assets: ResMut<GameAssets>,
+    mut loading: ResMut<AssetsLoading>,
+    mut asset_errors: ResMut<AssetErrors>,
+) {
+    let path = FileAssetIo::get_root_path().join("assets").join(MANIFEST_FILE);
+    let manifest = fs::read_to_string(&path)
+                      .map_err(|e| e.to_string())
+                      .and_then(|contents| ron::de::from_str::<AssetManifest>(&contents).map_err(|e| e.to_string()));
+
+    // the menus and the campaign need at least one stage
+    let manifest = manifest.and_then(|manifest| if manifest.levels.is_empty() {
+                                         Err("it lists no levels".to_string())
+                                     } else {
+                                         Ok(manifest)
+                                     });
+
+    let manifest = match manifest {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            println!("Could not load {:?}: {}", path, e);
+            asset_errors.manifest = Some(e);
+            AssetManifest::default()
+        }
+    };
+
+    *game_assets = GameAssets {
+        shaders: load_handles(&server, &manifest.shaders),
+        fonts: load_handles(&server, &manifest.fonts),
+        models: load_handles(&server, &manifest.models),
+        sounds: load_handles(&server, &manifest.sounds),
+        levels: load_handles(&server, &manifest.levels),
+    };
+    loading.asset_handles.extend(game_assets.untyped_handles());
+}
+
+// the font is one of the assets being loaded, so this text shows up once it's ready
+fn setup_loading(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    mut loading_timer: ResMut<LoadingTimer>,
+) {
+    loading_timer.0.reset();
+    ui::spawn_screen_text(&mut commands, &ui_font, 40.0);
+}
+
+fn asset_path(server: &AssetServer, handle: &HandleUntyped) -> String {
+    server.get_handle_path(handle)
+          .map_or("<unknown>".to_string(), |path| path.path().display().to_string())
+}
+
+// bevy only marks an asset as failed when reading its file fails and just logs why,
+// so the file is read again here to get the error to show
+fn read_error(path: &Path) -> String {
+    match fs::read(path) {
+        Err(e) => e.to_string(),
+        Ok(_) => "could not be read, but it can be now".to_string(),
+    }
+}
+
+fn check_assets_ready(
     mut state: ResMut<State<crate::AppState>>,
     server: Res<AssetServer>,
+    time: Res<Time>,
     loading: Res<AssetsLoading>,
+    mut loading_timer: ResMut<LoadingTimer>,
+    mut asset_errors: ResMut<AssetErrors>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
 ) {
-    println!("Loading...");
-    use bevy::asset::LoadState;
-
-    let mut ready = true;
+    let mut loaded = 0;
+    let mut failed = vec!();
+    let mut pending = vec!();
 
     for handle in loading.asset_handles.iter() {
         match server.get_load_state(handle) {
-            LoadState::Failed => {
-                // one of our assets had an error
-            }
-            LoadState::Loaded => {
-            }
-            _ => {
-                ready = false;
-            }
+            LoadState::Failed => failed.push(handle),
+            LoadState::Loaded => loaded += 1,
+            _ => pending.push(handle),
         }
     }
 
-    if ready {
-        state.set(crate::AppState::InGame).unwrap();
+    ui::set_screen_text(&mut texts, &format!("LOADING\n\n{} / {}", loaded, loading.asset_handles.len()));
+
+    let timed_out = loading_timer.0.tick(time.delta()).finished();
+    if failed.is_empty() && !timed_out && asset_errors.manifest.is_none() {
+        if pending.is_empty() {
+            state.set(crate::AppState::MainMenu).unwrap();
+        }
+        return;
+    }
+
+    // a file that can't be parsed stays loading forever, so the timeout catches those
+    let asset_dir = FileAssetIo::get_root_path().join("assets");
+    let manifest_error = asset_errors.manifest.clone();
+    asset_errors.failed = failed.iter().map(|handle| {
+                              let path = asset_path(&server, handle);
+                              let reason = read_error(&asset_dir.join(&path));
+                              (path, reason)
+                          })
+                          .chain(pending.iter().filter(|_| timed_out).map(|handle| {
+                              (asset_path(&server, handle),
+                               format!("still loading after {}s, it may be invalid", LOADING_TIMEOUT_SECONDS))
+                          }))
+                          .chain(manifest_error.iter().map(|e| {
+                              (MANIFEST_FILE.to_string(), format!("{}, fix it and restart", e))
+                          }))
+                          .collect();
+
+    for (path, reason) in asset_errors.failed.iter() {
+        println!("Asset {} failed: {}", path, reason);
+    }
+    state.push(crate::AppState::AssetError).unwrap();
+}
+
+fn setup_asset_error(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    mut selection: ResMut<AssetErrorSelection>,
+) {
+    selection.0 = 0;
+    ui::spawn_screen_text(&mut commands, &ui_font, 28.0);
+}
+
+fn asset_error_menu(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut selection: ResMut<AssetErrorSelection>,
+    server: Res<AssetServer>,
+    asset_errors: Res<AssetErrors>,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    // the manifest is only read on startup, so there's nothing a retry could fix
+    let items = if asset_errors.manifest.is_some() { &ERROR_ITEMS[1..] } else { &ERROR_ITEMS[..] };
+    selection.0 = ui::navigate_menu(&menu_input, selection.0, items.len());
+
+    let mut title = "SOME ASSETS FAILED TO LOAD\n\n".to_string();
+    for (path, reason) in asset_errors.failed.iter() {
+        title.push_str(&format!("{} - {}\n", path, reason));
+    }
+    ui::set_screen_text(&mut texts, &ui::menu_text(&title, items, selection.0));
+
+    if !menu_input.pressed(GameButton::Action) {
+        return;
+    }
+    menu_input.consume();
+
+    match items[selection.0] {
+        "RETRY" => {
+            // a failed asset has nothing committed, so loading the same path again starts over
+            for (path, _) in asset_errors.failed.iter() {
+                server.load_untyped(path.as_str());
+            }
+            state.pop().unwrap();
+        },
+        _ => app_exit_events.send(AppExit),
     }
 }
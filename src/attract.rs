@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::{Depth, Direction, Position, bullet, campaign, difficulty, enemy, hud, player, replay, settings, ui,
+            game_controller::{MenuInput, PilotInput}, input_map::Action};
+
+static DEMO_SECONDS:f32 = 60.0;
+// anything reaching the ship's lane sooner than this is worth moving out of the way of
+static DANGER_SECONDS:f32 = 0.35;
+static DIRECTIONS: [Direction; 10] = [
+    Direction::Up, Direction::Down, Direction::Left, Direction::Right,
+    Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight,
+    Direction::Forward, Direction::Back,
+];
+
+// the demo is an ordinary InGame run with the pilot or a replay flying, so the rest of
+// the game checks this to keep it from pausing, continuing, unlocking or recording anything
+#[derive(Default)]
+pub struct AttractMode {
+    pub active: bool,
+}
+
+struct IdleTimer(Timer);
+struct DemoTimer(Timer);
+
+#[derive(Copy, Clone)]
+struct LaneThreat {
+    // seconds until the nearest hostile reaches the ship
+    time: f32,
+    target: bool,
+}
+
+pub struct AttractPlugin;
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AttractMode>()
+           .init_resource::<PilotInput>()
+           .insert_resource(IdleTimer(Timer::from_seconds(1.0, false)))
+           .insert_resource(DemoTimer(Timer::from_seconds(DEMO_SECONDS, false)))
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::MainMenu)
+                   .with_system(stop_attract.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::MainMenu)
+                   .with_system(check_idle.system())
+           )
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(setup_demo.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(pilot_ship.system())
+                   .with_system(update_demo.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+fn stop_attract(
+    mut attract: ResMut<AttractMode>,
+    mut pilot_input: ResMut<PilotInput>,
+    mut idle_timer: ResMut<IdleTimer>,
+    settings: Res<settings::Settings>,
+) {
+    attract.active = false;
+    pilot_input.active = false;
+    pilot_input.actions.clear();
+    idle_timer.0 = Timer::from_seconds(settings.attract_idle_seconds, false);
+}
+
+fn check_idle(
+    mut state: ResMut<State<crate::AppState>>,
+    time: Res<Time>,
+    mut menu_input: ResMut<MenuInput>,
+    mut idle_timer: ResMut<IdleTimer>,
+    mut attract: ResMut<AttractMode>,
+    mut pilot_input: ResMut<PilotInput>,
+    mut campaign: ResMut<campaign::Campaign>,
+) {
+    if !menu_input.just_pressed.is_empty() {
+        idle_timer.0.reset();
+        return;
+    }
+
+    if idle_timer.0.tick(time.delta()).just_finished() {
+        attract.active = true;
+        pilot_input.active = true;
+        campaign.current = 0;
+        menu_input.consume();
+        state.set(crate::AppState::InGame).unwrap();
+    }
+}
+
+fn setup_demo(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    attract: Res<AttractMode>,
+    mut demo_timer: ResMut<DemoTimer>,
+) {
+    if !attract.active {
+        return;
+    }
+
+    demo_timer.0.reset();
+    ui::spawn_screen_text(&mut commands, &ui_font, 32.0);
+}
+
+// ends the demo on any button, when the time is up or when the run would have ended
+fn update_demo(
+    mut state: ResMut<State<crate::AppState>>,
+    time: Res<Time>,
+    attract: Res<AttractMode>,
+    playback: Res<replay::Playback>,
+    campaign: Res<campaign::Campaign>,
+    timeline: Res<enemy::StageTimeline>,
+    mut menu_input: ResMut<MenuInput>,
+    mut demo_timer: ResMut<DemoTimer>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+    players: Query<&player::PlayerStatus>,
+    enemies: Query<&enemy::Enemy>,
+) {
+    if !attract.active {
+        return;
+    }
+
+    let stage_over = timeline.next >= campaign.current_stage().timeline.len() && enemies.iter().next().is_none();
+    let ship_lost = players.iter().all(|status| status.lives == 0);
+
+    // a replay runs until its recording does
+    let out_of_time =
+        if playback.active() {
+            ui::set_screen_text(&mut texts, "REPLAY\n\nPRESS ANY BUTTON");
+            playback.finished
+        } else {
+            ui::set_screen_text(&mut texts, "DEMO\n\nPRESS ANY BUTTON");
+            demo_timer.0.tick(time.delta()).finished()
+        };
+
+    if !menu_input.just_pressed.is_empty() || out_of_time || stage_over || ship_lost {
+        menu_input.consume();
+        state.set(crate::AppState::MainMenu).unwrap();
+    }
+}
+
+// picks the lane to be in from how soon something reaches each one and whether
+// there's anything to shoot in it, then presses the buttons a player would
+fn pilot_ship(
+    attract: Res<AttractMode>,
+    playback: Res<replay::Playback>,
+    difficulty: Res<difficulty::Difficulty>,
+    mut pilot_input: ResMut<PilotInput>,
+    players: Query<(&player::Moveable, &Transform, Option<&player::DepthLanes>), With<player::Player>>,
+    enemies: Query<(&enemy::Enemy, &Transform)>,
+    bullets: Query<(&bullet::Bullet, &Transform)>,
+) {
+    if !attract.active || playback.active() {
+        return;
+    }
+
+    pilot_input.actions.clear();
+    let (moveable, ship, depth_lanes) = match players.iter().next() {
+        Some(player) => player,
+        None => return,
+    };
+
+    let mut threats: HashMap<(Position, Depth), LaneThreat> = HashMap::new();
+    let mut add_threat = |transform: &Transform, depth: Option<Depth>, speed: f32, target: bool| {
+        let distance = transform.translation.x - ship.translation.x;
+        if distance < -1.0 {
+            return; // already behind the ship
+        }
+
+        if let Some(lane) = player::lane_at(Vec2::new(transform.translation.z, transform.translation.y)) {
+            // no row means it's in all of them
+            let depths = match depth {
+                Some(depth) => vec!(depth),
+                None => vec!(Depth::Back, Depth::Middle, Depth::Front),
+            };
+            for depth in depths {
+                let threat = threats.entry((lane, depth)).or_insert(LaneThreat { time: f32::INFINITY, target: false });
+                threat.time = threat.time.min(distance.max(0.0) / speed);
+                threat.target |= target;
+            }
+        }
+    };
+
+    for (enemy, transform) in enemies.iter() {
+        add_threat(transform, enemy.depth, enemy::SPEED, true);
+    }
+    for (bullet, transform) in bullets.iter() {
+        if let bullet::Owner::Enemy = bullet.owner {
+            add_threat(transform, bullet.depth, bullet::SPEED * difficulty.multipliers.enemy_bullet_speed, false);
+        }
+    }
+
+    // safe lanes first, then ones with something to shoot, then whichever is clear the longest
+    let score = |cell: &(Position, Depth)| {
+        let threat = threats.get(cell).copied().unwrap_or(LaneThreat { time: f32::INFINITY, target: false });
+        (threat.time >= DANGER_SECONDS, threat.target, threat.time)
+    };
+
+    let current = (moveable.position(), moveable.depth());
+    let mut best = (None, score(&current));
+    for direction in DIRECTIONS.iter() {
+        let next = match direction {
+            // only with the depth rows on, otherwise the demo keeps to the middle row
+            Direction::Forward | Direction::Back if depth_lanes.is_none() => None,
+            Direction::Forward | Direction::Back => {
+                let depth = player::neighbor_depth(&current.1, *direction);
+                if depth != current.1 { Some((current.0, depth)) } else { None }
+            },
+            _ => player::neighbor_lane(&current.0, *direction).map(|lane| (lane, current.1)),
+        };
+        if let Some(cell) = next {
+            let cell_score = score(&cell);
+            let (best_safe, best_target, best_time) = best.1;
+            if (cell_score.0, cell_score.1) > (best_safe, best_target)
+            || ((cell_score.0, cell_score.1) == (best_safe, best_target) && cell_score.2 > best_time) {
+                best = (Some(*direction), cell_score);
+            }
+        }
+    }
+
+    if let Some(direction) = best.0 {
+        let actions: &[Action] = match direction {
+            Direction::Up => &[Action::MoveUp],
+            Direction::Down => &[Action::MoveDown],
+            Direction::Left => &[Action::MoveLeft],
+            Direction::Right => &[Action::MoveRight],
+            Direction::UpLeft => &[Action::MoveUp, Action::MoveLeft],
+            Direction::UpRight => &[Action::MoveUp, Action::MoveRight],
+            Direction::DownLeft => &[Action::MoveDown, Action::MoveLeft],
+            Direction::DownRight => &[Action::MoveDown, Action::MoveRight],
+            Direction::Forward => &[Action::MoveForward],
+            Direction::Back => &[Action::MoveBack],
+        };
+        pilot_input.actions.extend(actions);
+    }
+
+    if score(&current).1 {
+        pilot_input.actions.push(Action::Fire);
+    }
+}
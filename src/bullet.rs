@@ -1,37 +1,73 @@
 use bevy::prelude::*;
+use bevy::app::Events;
+
+use crate::{Depth, difficulty, field, replay};
 
 pub struct Bullet {
-    direction: Vec3
+    direction: Vec3,
+    pub owner: Owner,
+    // the row it was fired from, None hits every row
+    pub depth: Option<Depth>,
 }
 pub struct BulletEvent {
     pub start: Vec3,
-    pub direction: Vec3
+    pub direction: Vec3,
+    pub owner: Owner,
+    pub depth: Option<Depth>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Owner {
+    Player(usize),
+    Enemy,
 }
 
-static SPEED:f32 = 90.0;
+pub static SPEED:f32 = 90.0;
+// enemy shots come back toward the player, slower so they can be dodged
+static ENEMY_SPEED:f32 = 45.0;
 static BULLET_DESPAWN_POINT:f32 = 500.0;
+static ENEMY_BULLET_DESPAWN_POINT:f32 = -20.0;
 
 pub struct BulletPlugin;
 impl Plugin for BulletPlugin {
     fn build(&self, app: &mut AppBuilder) {
        app.add_event::<BulletEvent>()
+          .add_system_set_to_stage(replay::FIXED_UPDATE,
+              replay::on_tick()
+                  .with_system(update_bullets.system().label("movement").after("player_input"))
+                  .with_system(handle_bullet_event.system().after("player_input").after("enemy_fire"))
+          )
           .add_system_set(
-              SystemSet::on_update(crate::AppState::InGame)
-                  .with_system(update_bullets.system())
-                  .with_system(handle_bullet_event.system())
+              SystemSet::on_exit(crate::AppState::InGame)
+                  .with_system(clear_bullet_events.system())
           );
     }
 }
 
+pub fn enemy_speed(difficulty: &difficulty::Difficulty) -> f32 {
+    ENEMY_SPEED * difficulty.multipliers.enemy_bullet_speed
+}
+
+// shots fired on the last frame would otherwise be spawned into the next run
+fn clear_bullet_events(
+    mut bullet_events: ResMut<Events<BulletEvent>>,
+) {
+    bullet_events.clear();
+}
+
 fn update_bullets(
     mut commands: Commands,
     mut bullets: Query<(Entity, &Bullet, &mut Transform)>,
-    time: Res<Time>,
+    difficulty: Res<difficulty::Difficulty>,
 ) {
     for (entity, bullet, mut transform) in bullets.iter_mut() {
-        transform.translation += bullet.direction * time.delta_seconds() * SPEED;
+        let speed = match bullet.owner {
+            Owner::Player(_) => SPEED,
+            Owner::Enemy => enemy_speed(&difficulty),
+        };
+        transform.translation += bullet.direction * replay::TICK_SECONDS * speed;
 
-        if transform.translation.x > BULLET_DESPAWN_POINT {
+        if transform.translation.x > BULLET_DESPAWN_POINT || transform.translation.x < ENEMY_BULLET_DESPAWN_POINT {
             commands.entity(entity).despawn_recursive();
         }
     }
@@ -39,18 +75,21 @@ fn update_bullets(
 
 fn handle_bullet_event(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
+    game_meshes: Res<field::GameMeshes>,
     mut bullet_event_reader: EventReader<BulletEvent>,
 ) {
     for event in bullet_event_reader.iter() {
         commands.spawn()
                 .insert_bundle(PbrBundle {
-                    mesh: meshes.add(Mesh::from(shape::Cube { size: 0.3 })),
+                    mesh: game_meshes.bullet.clone(),
                     transform: Transform::from_translation(event.start),
                     ..Default::default()
                 })
                 .insert(Bullet {
-                    direction: event.direction
-                });
+                    direction: event.direction,
+                    owner: event.owner,
+                    depth: event.depth,
+                })
+                .insert(field::LevelEntity);
     }
 }
@@ -1,16 +1,62 @@
 use bevy::prelude::*;
-use crate::field::LevelReady;
+use rand::Rng;
+use crate::{player, settings, field::LevelReady};
+
+static SHAKE_SECONDS:f32 = 0.3;
+static SHAKE_STRENGTH:f32 = 0.4;
+
+struct ScreenShake(Timer);
 
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system_set(
+        app.insert_resource(ScreenShake(Timer::from_seconds(SHAKE_SECONDS, false)))
+           .add_system_set(
                SystemSet::on_update(crate::AppState::InGame)
                          .with_system(update_camera.system())
+                         .with_system(shake_camera.system())
            );
     }
 }
 
+fn camera_transform() -> Transform {
+    let mut transform = Transform::default();
+
+    transform.translation = Vec3::new(-16.0, 5.0, 0.0);
+    transform.rotation = Quat::from_axis_angle(Vec3::new(0.0, -0.9905375, 0.0), std::f32::consts::PI / 2.0);
+
+    transform
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<settings::Settings>,
+    mut shake: ResMut<ScreenShake>,
+    mut player_hit_event_reader: EventReader<player::PlayerHitEvent>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    if player_hit_event_reader.iter().next().is_some() && settings.screen_shake {
+        shake.0.reset();
+    }
+
+    if shake.0.finished() {
+        return;
+    }
+
+    let resting = camera_transform().translation;
+    let remaining = 1.0 - shake.0.tick(time.delta()).percent();
+    let mut rng = rand::thread_rng();
+    for mut transform in cameras.iter_mut() {
+        transform.translation = if shake.0.finished() {
+            resting
+        } else {
+            resting + Vec3::new(0.0,
+                                rng.gen_range(-1.0..1.0),
+                                rng.gen_range(-1.0..1.0)) * SHAKE_STRENGTH * remaining
+        };
+    }
+}
+
 fn update_camera(
     mut cameras: Query<&mut Transform, Without<MainCamera>>,
     keyboard_input: Res<Input<KeyCode>>,
@@ -42,10 +88,7 @@ pub fn create_camera(
         return; // level isn't loaded so we'll try again later
     }
 
-    let mut transform = Transform::default();
-
-    transform.translation = Vec3::new(-16.0, 5.0, 0.0);
-    transform.rotation = Quat::from_axis_angle(Vec3::new(0.0, -0.9905375, 0.0), std::f32::consts::PI / 2.0);
+    let transform = camera_transform();
 
     if let Ok(mut camera_transform) = cameras.single_mut() {
         *camera_transform = transform;
@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use serde::{Serialize, Deserialize};
+
+use crate::{Position, asset_loader, attract, difficulty, enemy, glyphs, hud, save, sound, ui, game_controller::{GameButton, MenuInput}, input_map::Action};
+
+static PROGRESS_FILE:&str = "campaign.ron";
+static PROGRESS_VERSION:u32 = 1;
+
+#[derive(Clone)]
+pub struct FieldTheme {
+    pub clear_color: Color,
+    pub speed: f32,
+}
+
+#[derive(Clone)]
+pub struct SpawnEntry {
+    pub time: f32,
+    // None picks a random lane when the enemy spawns
+    pub lane: Option<Position>,
+    pub boss: bool,
+}
+
+// a stage is loaded from a .level file, its id is the file's key in assets/manifest.ron
+#[derive(Clone, TypeUuid)]
+#[uuid = "8f3c7a52-6b1e-4d0a-9c2f-5e7d1b4a6f30"]
+pub struct Stage {
+    pub id: String,
+    pub name: String,
+    pub theme: FieldTheme,
+    pub timeline: Vec<SpawnEntry>,
+    // a key into the sounds in assets/manifest.ron
+    pub music: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Campaign {
+    pub stages: Vec<Stage>,
+    pub current: usize,
+}
+
+impl Campaign {
+    pub fn current_stage(&self) -> &Stage {
+        &self.stages[self.current]
+    }
+}
+
+// a run of enemies spawned one after another, cycling through the given lanes
+#[derive(Deserialize)]
+struct Wave {
+    start: f32,
+    interval: f32,
+    count: usize,
+    lanes: Vec<Option<Position>>,
+}
+
+// a .level file, every stage ends on a boss in the center lane at the given time
+#[derive(Deserialize)]
+struct LevelFile {
+    name: String,
+    clear_color: String,
+    speed: f32,
+    music: Option<String>,
+    waves: Vec<Wave>,
+    boss: f32,
+}
+
+fn timeline(level: &LevelFile) -> Vec<SpawnEntry> {
+    let mut timeline = vec!();
+    for wave in level.waves.iter() {
+        for i in 0..wave.count {
+            timeline.push(SpawnEntry {
+                time: wave.start + wave.interval * i as f32,
+                lane: wave.lanes.get(i % wave.lanes.len().max(1)).copied().flatten(),
+                boss: false,
+            });
+        }
+    }
+    timeline.push(SpawnEntry {
+        time: level.boss,
+        lane: Some(Position::Center),
+        boss: true,
+    });
+    timeline.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+    timeline
+}
+
+#[derive(Default)]
+struct LevelLoader;
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let level: LevelFile = ron::de::from_bytes(bytes)?;
+            let clear_color = Color::hex(&level.clear_color)
+                                    .map_err(|e| anyhow::anyhow!("bad clear color {}: {:?}", level.clear_color, e))?;
+            load_context.set_default_asset(LoadedAsset::new(Stage {
+                id: String::new(),
+                name: level.name.clone(),
+                theme: FieldTheme { clear_color, speed: level.speed },
+                timeline: timeline(&level),
+                music: level.music.clone(),
+            }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CampaignProgress {
+    pub unlocked: Vec<String>,
+}
+
+impl Default for CampaignProgress {
+    fn default() -> Self {
+        CampaignProgress {
+            unlocked: vec!("stage_1".to_string()),
+        }
+    }
+}
+
+impl CampaignProgress {
+    pub fn load() -> Self {
+        save::load(PROGRESS_FILE, PROGRESS_VERSION).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        save::save(PROGRESS_FILE, PROGRESS_VERSION, self);
+    }
+
+    pub fn is_unlocked(&self, stage: &Stage) -> bool {
+        self.unlocked.contains(&stage.id)
+    }
+}
+
+pub struct StageClearedEvent {
+    pub stage: String,
+}
+
+pub struct CampaignPlugin;
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<StageClearedEvent>()
+           .add_asset::<Stage>()
+           .init_asset_loader::<LevelLoader>()
+           .init_resource::<Campaign>()
+           .insert_resource(CampaignProgress::load())
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Loading)
+                   .with_system(build_campaign.system())
+           )
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(play_music.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(check_stage_clear.system().after("check_game_over"))
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(stop_music.system())
+           )
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::LevelSelect)
+                   .with_system(setup_level_select.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::LevelSelect)
+                   .with_system(level_select.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::LevelSelect)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+// stages are played in the order of their keys in the manifest
+fn build_campaign(
+    game_assets: Res<asset_loader::GameAssets>,
+    levels: Res<Assets<Stage>>,
+    mut campaign: ResMut<Campaign>,
+) {
+    let mut keys: Vec<&String> = game_assets.levels.keys().collect();
+    keys.sort();
+
+    campaign.stages = keys.into_iter()
+                          .filter_map(|key| {
+                              let mut stage = levels.get(&game_assets.levels[key])?.clone();
+                              stage.id = key.clone();
+                              Some(stage)
+                          })
+                          .collect();
+    campaign.current = 0;
+}
+
+fn play_music(
+    game_assets: Res<asset_loader::GameAssets>,
+    mut music: ResMut<sound::Music>,
+    campaign: Res<Campaign>,
+) {
+    match campaign.current_stage().music.as_ref() {
+        Some(key) => music.play(game_assets.sound(key)),
+        None => music.stop(),
+    }
+}
+
+fn stop_music(
+    mut music: ResMut<sound::Music>,
+) {
+    music.stop();
+}
+
+fn check_stage_clear(
+    mut state: ResMut<State<crate::AppState>>,
+    campaign: Res<Campaign>,
+    mut progress: ResMut<CampaignProgress>,
+    timeline: Res<enemy::StageTimeline>,
+    attract: Res<attract::AttractMode>,
+    enemies: Query<&enemy::Enemy>,
+    mut stage_cleared_event_writer: EventWriter<StageClearedEvent>,
+) {
+    if attract.active {
+        return;
+    }
+
+    if timeline.next < campaign.current_stage().timeline.len() || enemies.iter().next().is_some() {
+        return;
+    }
+
+    // losing the last life on the same frame wins, the stage isn't cleared
+    if state.set(crate::AppState::Results).is_err() {
+        println!("Not clearing the stage, another state change is pending");
+        return;
+    }
+
+    if let Some(next) = campaign.stages.get(campaign.current + 1) {
+        if !progress.is_unlocked(next) {
+            progress.unlocked.push(next.id.clone());
+            progress.save();
+        }
+    }
+
+    stage_cleared_event_writer.send(StageClearedEvent { stage: campaign.current_stage().id.clone() });
+}
+
+fn setup_level_select(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 40.0);
+}
+
+fn level_select(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut campaign: ResMut<Campaign>,
+    mut difficulty: ResMut<difficulty::Difficulty>,
+    progress: Res<CampaignProgress>,
+    glyphs: Res<glyphs::Glyphs>,
+    mut back_selected: Local<bool>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    // only unlocked stages can be selected, and unlocks always happen in order
+    let unlocked = campaign.stages.iter()
+                                  .take_while(|stage| progress.is_unlocked(stage))
+                                  .count()
+                                  .max(1);
+    campaign.current = campaign.current.min(unlocked - 1);
+
+    // BACK sits just below the last unlocked stage
+    if menu_input.pressed(GameButton::Up) {
+        if *back_selected {
+            *back_selected = false;
+        } else if campaign.current > 0 {
+            campaign.current -= 1;
+        }
+    }
+    if menu_input.pressed(GameButton::Down) {
+        if campaign.current < unlocked - 1 {
+            campaign.current += 1;
+        } else {
+            *back_selected = true;
+        }
+    }
+    if menu_input.pressed(GameButton::Left) {
+        let level = difficulty.level.previous();
+        difficulty.set_level(level);
+    }
+    if menu_input.pressed(GameButton::Right) {
+        let level = difficulty.level.next();
+        difficulty.set_level(level);
+    }
+    if menu_input.pressed(GameButton::Action) {
+        if *back_selected {
+            *back_selected = false;
+            menu_input.consume();
+            state.set(crate::AppState::MainMenu).unwrap();
+        } else {
+            menu_input.consume();
+            state.set(crate::AppState::InGame).unwrap();
+        }
+        return;
+    }
+    if menu_input.pressed(GameButton::Start) {
+        menu_input.consume();
+        state.set(crate::AppState::Achievements).unwrap();
+        return;
+    }
+
+    let mut value = "SELECT STAGE\n\n".to_string();
+    for (i, stage) in campaign.stages.iter().enumerate() {
+        let name = if i < unlocked { stage.name.as_str() } else { "LOCKED" };
+        if i == campaign.current && !*back_selected {
+            value.push_str(&format!("> {}. {} <\n", i + 1, name));
+        } else {
+            value.push_str(&format!("{}. {}\n", i + 1, name));
+        }
+    }
+    value.push_str(if *back_selected { "> BACK <\n" } else { "BACK\n" });
+    value.push_str(&format!("\n< DIFFICULTY: {} >", difficulty.level.name()));
+    value.push_str(&format!("\n\n{} ACHIEVEMENTS", glyphs.prompt(Action::Pause)));
+    ui::set_screen_text(&mut texts, &value);
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::{hud, profiles, settings, ui,
+            game_controller::{self, GameButton, MenuInput},
+            input_map::{self, Action, AxisDirection, Binding, InputMap}};
+
+static AXES: [GamepadAxisType; 8] = [
+    GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY,
+    GamepadAxisType::RightStickX, GamepadAxisType::RightStickY,
+    GamepadAxisType::LeftZ, GamepadAxisType::RightZ,
+    GamepadAxisType::DPadX, GamepadAxisType::DPadY,
+];
+// further than the InputMap needs so a resting stick can't get bound by accident
+static CAPTURE_THRESHOLD:f32 = 0.75;
+static STICK_ROWS:usize = 5;
+
+#[derive(Default)]
+struct ControlsScreen {
+    selected: usize,
+    // the action waiting for a new binding, and whether the press that picked it has been let go
+    rebinding: Option<(Action, bool)>,
+}
+
+pub struct ControlsPlugin;
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ControlsScreen>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Controls)
+                   .with_system(setup_controls.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Controls)
+                   .with_system(controls_menu.system())
+           )
+           .add_system_set(
+               SystemSet::on_pause(crate::AppState::Controls)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           .add_system_set(
+               SystemSet::on_resume(crate::AppState::Controls)
+                   .with_system(spawn_controls_text.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Controls)
+                   .with_system(save_bindings.system())
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+fn setup_controls(
+    commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    mut controls_screen: ResMut<ControlsScreen>,
+) {
+    *controls_screen = ControlsScreen::default();
+    spawn_controls_text(commands, ui_font);
+}
+
+fn spawn_controls_text(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 26.0);
+}
+
+// the first key, button or stick push since the rebind started
+fn captured_binding(
+    keyboard_input: &Input<KeyCode>,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    gamepad: Option<Gamepad>,
+) -> Option<Binding> {
+    if let Some(key_code) = keyboard_input.get_just_pressed().next() {
+        return Some(Binding::Key(*key_code));
+    }
+
+    let gamepad = gamepad?;
+    if let Some(GamepadButton(_, button_type)) = buttons.get_just_pressed().find(|button| button.0 == gamepad) {
+        return Some(Binding::Button(*button_type));
+    }
+
+    AXES.iter().find_map(|axis_type| {
+        let value = axes.get(GamepadAxis(gamepad, *axis_type)).unwrap_or(0.0);
+        if value > CAPTURE_THRESHOLD {
+            Some(Binding::Axis(*axis_type, AxisDirection::Positive))
+        } else if value < -CAPTURE_THRESHOLD {
+            Some(Binding::Axis(*axis_type, AxisDirection::Negative))
+        } else {
+            None
+        }
+    })
+}
+
+fn controls_menu(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut input_map: ResMut<InputMap>,
+    mut controls_screen: ResMut<ControlsScreen>,
+    keyboard_input: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad: Option<Res<game_controller::GameController>>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    let mut items: Vec<String> = input_map::ACTIONS.iter().map(|action| {
+                                     let bindings: Vec<String> = input_map.bindings(*action).iter().map(|binding| binding.name()).collect();
+                                     format!("{} - {}", action.name(), bindings.join(", "))
+                                 })
+                                 .collect();
+    let stick_settings = &input_map.stick_settings;
+    items.push(format!("STICK DEADZONE < {} >", percent_text(stick_settings.inner_deadzone)));
+    items.push(format!("STICK OUTER EDGE < {} >", percent_text(stick_settings.outer_deadzone)));
+    items.push(format!("DIRECTION SNAP < {} DEG >", stick_settings.snap_angle.round() as u32));
+    items.push(format!("PRESS AT < {} >", percent_text(stick_settings.press_threshold)));
+    items.push(format!("RELEASE AT < {} >", percent_text(stick_settings.release_threshold)));
+    items.push("GAMEPAD SETUP".to_string());
+    items.push("RESET DEFAULTS".to_string());
+    items.push("BACK".to_string());
+
+    if let Some((action, listening)) = controls_screen.rebinding {
+        ui::set_screen_text(&mut texts, &format!("PRESS A KEY OR BUTTON FOR\n\n{}", action.name()));
+
+        if !listening {
+            // whatever picked this action shouldn't become its binding
+            if input_map.just_released(Action::Fire) || !input_map.pressed(Action::Fire) {
+                controls_screen.rebinding = Some((action, true));
+            }
+            return;
+        }
+
+        if let Some(binding) = captured_binding(&keyboard_input, &buttons, &axes, gamepad.map(|gamepad| gamepad.0)) {
+            input_map.rebind(action, binding);
+            controls_screen.rebinding = None;
+            menu_input.consume();
+        }
+        return;
+    }
+
+    controls_screen.selected = ui::navigate_menu(&menu_input, controls_screen.selected, items.len());
+    let step = ui::menu_step(&menu_input);
+    if step != 0 {
+        if let Some(row) = controls_screen.selected.checked_sub(input_map::ACTIONS.len()) {
+            adjust_stick_settings(&mut input_map.stick_settings, row, step);
+        }
+    }
+
+    let item_names: Vec<&str> = items.iter().map(|item| item.as_str()).collect();
+    ui::set_screen_text(&mut texts, &ui::menu_text("CONTROLS", &item_names, controls_screen.selected));
+
+    if !menu_input.pressed(GameButton::Action) {
+        return;
+    }
+    menu_input.consume();
+
+    let selected = controls_screen.selected;
+    if let Some(action) = input_map::ACTIONS.get(selected) {
+        controls_screen.rebinding = Some((*action, false));
+    } else if selected < input_map::ACTIONS.len() + STICK_ROWS {
+        // these are changed with left and right
+    } else if selected == input_map::ACTIONS.len() + STICK_ROWS {
+        state.push(crate::AppState::GamepadSetup).unwrap();
+    } else if selected == input_map::ACTIONS.len() + STICK_ROWS + 1 {
+        // replacing the whole map would forget what's held and fire it again
+        let defaults = InputMap::default();
+        input_map.bindings = defaults.bindings;
+        input_map.stick_settings = defaults.stick_settings;
+    } else {
+        state.pop().unwrap();
+    }
+}
+
+fn percent_text(value: f32) -> String {
+    format!("{}%", (value * 100.0).round() as u32)
+}
+
+// snapped to whole steps so repeated presses don't drift
+// max and min rather than clamp, which panics if the limits ever cross
+fn adjust(value: f32, step: i32, amount: f32, min: f32, max: f32) -> f32 {
+    (((value / amount).round() + step as f32) * amount).min(max).max(min)
+}
+
+// keeps the inner deadzone under the outer one and the release point under the press point
+fn adjust_stick_settings(stick_settings: &mut input_map::StickSettings, row: usize, step: i32) {
+    match row {
+        0 => stick_settings.inner_deadzone = adjust(stick_settings.inner_deadzone, step, 0.05, 0.0, stick_settings.outer_deadzone - 0.1),
+        1 => stick_settings.outer_deadzone = adjust(stick_settings.outer_deadzone, step, 0.05, stick_settings.inner_deadzone + 0.1, 1.0),
+        2 => stick_settings.snap_angle = adjust(stick_settings.snap_angle, step, 5.0, 0.0, 45.0),
+        3 => {
+            stick_settings.press_threshold = adjust(stick_settings.press_threshold, step, 0.05, 0.1, 0.95);
+            stick_settings.release_threshold = stick_settings.release_threshold.min(stick_settings.press_threshold);
+        },
+        4 => stick_settings.release_threshold = adjust(stick_settings.release_threshold, step, 0.05, 0.05, stick_settings.press_threshold),
+        _ => (),
+    }
+}
+
+fn save_bindings(
+    input_map: Res<InputMap>,
+    mut settings: ResMut<settings::Settings>,
+    mut active_profile: ResMut<profiles::ActiveProfile>,
+) {
+    profiles::store_bindings(&mut settings, &mut active_profile, &input_map);
+    settings.save();
+}
@@ -0,0 +1,134 @@
+use serde::{Serialize, Deserialize};
+
+use crate::save;
+
+static CUSTOM_DIFFICULTY_FILE:&str = "difficulty.ron";
+static CUSTOM_DIFFICULTY_VERSION:u32 = 1;
+static BASE_LIVES:f32 = 3.0;
+// anything lower stalls the spawn timeline or makes enemies unkillable
+static MIN_MULTIPLIER:f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DifficultyLevel {
+    Easy,
+    Normal,
+    Hard,
+    Insane,
+    Custom,
+}
+
+impl Default for DifficultyLevel {
+    fn default() -> Self {
+        DifficultyLevel::Normal
+    }
+}
+
+impl DifficultyLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DifficultyLevel::Easy => "EASY",
+            DifficultyLevel::Normal => "NORMAL",
+            DifficultyLevel::Hard => "HARD",
+            DifficultyLevel::Insane => "INSANE",
+            DifficultyLevel::Custom => "CUSTOM",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DifficultyLevel::Easy => DifficultyLevel::Normal,
+            DifficultyLevel::Normal => DifficultyLevel::Hard,
+            DifficultyLevel::Hard => DifficultyLevel::Insane,
+            DifficultyLevel::Insane => DifficultyLevel::Custom,
+            DifficultyLevel::Custom => DifficultyLevel::Easy,
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        match self {
+            DifficultyLevel::Easy => DifficultyLevel::Custom,
+            DifficultyLevel::Normal => DifficultyLevel::Easy,
+            DifficultyLevel::Hard => DifficultyLevel::Normal,
+            DifficultyLevel::Insane => DifficultyLevel::Hard,
+            DifficultyLevel::Custom => DifficultyLevel::Insane,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Multipliers {
+    pub enemy_hp: f32,
+    pub enemy_bullet_speed: f32,
+    pub spawn_density: f32,
+    pub player_lives: f32,
+    pub score: f32,
+}
+
+impl Multipliers {
+    fn preset(level: DifficultyLevel) -> Option<Self> {
+        let (enemy_hp, enemy_bullet_speed, spawn_density, player_lives, score) =
+            match level {
+                DifficultyLevel::Easy => (0.5, 0.75, 0.75, 1.67, 0.5),
+                DifficultyLevel::Normal => (1.0, 1.0, 1.0, 1.0, 1.0),
+                DifficultyLevel::Hard => (2.0, 1.25, 1.5, 0.67, 1.5),
+                DifficultyLevel::Insane => (3.0, 1.5, 2.0, 0.34, 2.5),
+                DifficultyLevel::Custom => return None,
+            };
+
+        Some(Multipliers { enemy_hp, enemy_bullet_speed, spawn_density, player_lives, score })
+    }
+
+    // the custom file is edited by hand, so keep whatever's in it playable
+    fn validated(self) -> Self {
+        let valid = |value: f32| if value.is_finite() { value.max(MIN_MULTIPLIER) } else { 1.0 };
+        let validated = Multipliers {
+            enemy_hp: valid(self.enemy_hp),
+            enemy_bullet_speed: valid(self.enemy_bullet_speed),
+            spawn_density: valid(self.spawn_density),
+            player_lives: valid(self.player_lives),
+            score: if self.score.is_finite() { self.score.max(0.0) } else { 1.0 },
+        };
+
+        if validated != self {
+            println!("Fixed invalid custom difficulty {:?}", self);
+        }
+        validated
+    }
+}
+
+pub struct Difficulty {
+    pub level: DifficultyLevel,
+    pub multipliers: Multipliers,
+    // read from the data directory so it can be tuned by hand
+    custom: Multipliers,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        let normal = Multipliers::preset(DifficultyLevel::Normal).unwrap();
+        Difficulty {
+            level: DifficultyLevel::Normal,
+            multipliers: normal,
+            custom: save::load(CUSTOM_DIFFICULTY_FILE, CUSTOM_DIFFICULTY_VERSION).map_or(normal, Multipliers::validated),
+        }
+    }
+}
+
+impl Difficulty {
+    pub fn set_level(&mut self, level: DifficultyLevel) {
+        self.level = level;
+        self.multipliers = Multipliers::preset(level).unwrap_or(self.custom);
+    }
+
+    pub fn enemy_hp(&self, base: u32) -> u32 {
+        ((base as f32 * self.multipliers.enemy_hp).round() as u32).max(1)
+    }
+
+    pub fn player_lives(&self) -> u32 {
+        ((BASE_LIVES * self.multipliers.player_lives).round() as u32).max(1)
+    }
+
+    pub fn score(&self, points: u64) -> u64 {
+        (points as f32 * self.multipliers.score).round() as u64
+    }
+}
@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{Depth, Position, bullet, campaign, difficulty, field, pickup, player, replay, score};
+
+pub static SPEED:f32 = 20.0;
+static SPAWN_POINT:f32 = 120.0;
+static DESPAWN_POINT:f32 = -20.0;
+static HIT_DEPTH:f32 = 2.0;
+static LANE_WIDTH:f32 = 1.0;
+static GRAZE_WIDTH:f32 = 3.5;
+static DROP_CHANCE:f64 = 0.25;
+// bosses stop here and wait to be shot down
+static BOSS_HOLD_POINT:f32 = 60.0;
+static BOSS_HP:u32 = 60;
+static BOSS_POINTS:u32 = 5000;
+static FIRE_SECONDS:f32 = 3.0;
+static BOSS_FIRE_SECONDS:f32 = 0.8;
+// enemies hold their fire once they're this close, there'd be no time to dodge
+static MIN_FIRE_DISTANCE:f32 = 30.0;
+
+static DEPTHS: [Depth; 3] = [Depth::Back, Depth::Middle, Depth::Front];
+
+static LANES: [Position; 9] = [
+    Position::TopLeft, Position::TopCenter, Position::TopRight,
+    Position::Left, Position::Center, Position::Right,
+    Position::BottomLeft, Position::BottomCenter, Position::BottomRight,
+];
+
+pub struct Enemy {
+    pub hp: u32,
+    pub points: u32,
+    // the depth row it can hit and be hit in, None is every row
+    pub depth: Option<Depth>,
+    grazed: bool,
+    drops: bool,
+    reload: f32,
+}
+
+pub struct EnemyHitEvent {
+    pub player: usize,
+}
+
+// shown on the HUD's boss health bar while one is alive, the health left is on its Enemy
+pub struct Boss {
+    pub max_hp: u32,
+}
+
+// how far through the current stage's spawn timeline we are
+#[derive(Default)]
+pub struct StageTimeline {
+    pub elapsed: f32,
+    pub next: usize,
+}
+
+pub struct EnemyPlugin;
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<EnemyHitEvent>()
+           .init_resource::<StageTimeline>()
+           .add_system_set(
+              SystemSet::on_enter(crate::AppState::InGame)
+                  .with_system(reset_timeline.system())
+          )
+           .add_system_set_to_stage(replay::FIXED_UPDATE,
+              replay::on_tick()
+                  .with_system(spawn_enemies.system().label("movement").after("player_input"))
+                  .with_system(update_enemies.system().label("movement").after("player_input"))
+                  .with_system(enemy_fire.system().label("enemy_fire").after("movement"))
+                  .with_system(bullet_collisions.system().label("collisions").after("movement"))
+                  .with_system(check_grazes.system().label("collisions").after("movement"))
+                  .with_system(player_collisions.system().label("collisions").after("movement"))
+          );
+    }
+}
+
+fn reset_timeline(
+    mut timeline: ResMut<StageTimeline>,
+) {
+    *timeline = StageTimeline::default();
+}
+
+fn spawn_enemies(
+    mut commands: Commands,
+    game_meshes: Res<field::GameMeshes>,
+    mut timeline: ResMut<StageTimeline>,
+    campaign: Res<campaign::Campaign>,
+    difficulty: Res<difficulty::Difficulty>,
+    mut rng: ResMut<replay::GameRng>,
+    depth_lanes: Query<&player::DepthLanes>,
+) {
+    let depth_lanes = depth_lanes.iter().next().is_some();
+
+    // denser spawns play the same timeline faster
+    timeline.elapsed += replay::TICK_SECONDS * difficulty.multipliers.spawn_density;
+
+    let entries = &campaign.current_stage().timeline;
+    while let Some(entry) = entries.get(timeline.next) {
+        if entry.time > timeline.elapsed {
+            break;
+        }
+        timeline.next += 1;
+
+        let lane = entry.lane.unwrap_or_else(|| LANES[rng.0.gen_range(0..LANES.len())]);
+        let lane_translation = player::lane_translation(&lane);
+        // bosses fill every row
+        let depth = if depth_lanes && !entry.boss {
+            Some(DEPTHS[rng.0.gen_range(0..DEPTHS.len())])
+        } else {
+            None
+        };
+        let material = match depth {
+            Some(Depth::Front) => &game_meshes.front_enemy_material,
+            Some(Depth::Back) => &game_meshes.back_enemy_material,
+            _ => &game_meshes.enemy_material,
+        };
+
+        let (mesh, hp, points) = if entry.boss {
+            (&game_meshes.boss, difficulty.enemy_hp(BOSS_HP), BOSS_POINTS)
+        } else {
+            (&game_meshes.enemy, difficulty.enemy_hp(1), 100)
+        };
+
+        let mut enemy = commands.spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(Vec3::new(SPAWN_POINT, lane_translation.y, lane_translation.x)),
+                ..Default::default()
+            });
+        enemy.insert(Enemy {
+                hp,
+                points,
+                depth,
+                grazed: false,
+                drops: rng.0.gen_bool(DROP_CHANCE),
+                // so a wave doesn't fire in lockstep
+                reload: rng.0.gen_range(0.0..FIRE_SECONDS),
+            })
+            .insert(field::LevelEntity);
+
+        if entry.boss {
+            enemy.insert(Boss { max_hp: hp });
+        }
+    }
+}
+
+fn update_enemies(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &mut Transform, Option<&Boss>), With<Enemy>>,
+) {
+    for (entity, mut transform, boss) in enemies.iter_mut() {
+        transform.translation.x -= replay::TICK_SECONDS * SPEED;
+        if boss.is_some() {
+            transform.translation.x = transform.translation.x.max(BOSS_HOLD_POINT);
+        }
+
+        if transform.translation.x < DESPAWN_POINT {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn enemy_fire(
+    mut enemies: Query<(&mut Enemy, &Transform, Option<&Boss>)>,
+    players: Query<&Transform, With<player::Player>>,
+    mut rng: ResMut<replay::GameRng>,
+    mut bullet_event_writer: EventWriter<bullet::BulletEvent>,
+) {
+    let nearest = players.iter().map(|transform| transform.translation.x).fold(f32::INFINITY, f32::min);
+
+    for (mut enemy, transform, boss) in enemies.iter_mut() {
+        enemy.reload -= replay::TICK_SECONDS;
+        if enemy.reload > 0.0 || transform.translation.x - nearest < MIN_FIRE_DISTANCE {
+            continue;
+        }
+
+        // bosses spray shots down every lane instead of their own
+        let start = if boss.is_some() {
+            enemy.reload = BOSS_FIRE_SECONDS;
+            let lane = player::lane_translation(&LANES[rng.0.gen_range(0..LANES.len())]);
+            Vec3::new(transform.translation.x, lane.y, lane.x)
+        } else {
+            enemy.reload = FIRE_SECONDS;
+            transform.translation
+        };
+
+        bullet_event_writer.send(bullet::BulletEvent {
+            start,
+            direction: -Vec3::X,
+            owner: bullet::Owner::Enemy,
+            depth: enemy.depth,
+        });
+    }
+}
+
+pub fn lane_distance(a: Vec3, b: Vec3) -> f32 {
+    Vec2::new(a.z, a.y).distance(Vec2::new(b.z, b.y))
+}
+
+fn same_row(a: Option<Depth>, b: Option<Depth>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+// the same lane and depth row, and level along x
+fn touching(a: Vec3, a_depth: Option<Depth>, b: Vec3, b_depth: Option<Depth>) -> bool {
+    same_row(a_depth, b_depth) && lane_distance(a, b) <= LANE_WIDTH && (a.x - b.x).abs() <= HIT_DEPTH
+}
+
+fn bullet_collisions(
+    mut commands: Commands,
+    bullets: Query<(Entity, &bullet::Bullet, &Transform)>,
+    mut enemies: Query<(Entity, &mut Enemy, &Transform)>,
+    mut score_event_writer: EventWriter<score::ScoreEvent>,
+    mut drop_event_writer: EventWriter<pickup::PickupDropEvent>,
+    mut enemy_hit_event_writer: EventWriter<EnemyHitEvent>,
+) {
+    for (bullet_entity, bullet, bullet_transform) in bullets.iter() {
+        let player_id = match bullet.owner {
+            bullet::Owner::Player(id) => id,
+            bullet::Owner::Enemy => continue,
+        };
+
+        for (enemy_entity, mut enemy, enemy_transform) in enemies.iter_mut() {
+            if enemy.hp == 0 || !touching(bullet_transform.translation, bullet.depth, enemy_transform.translation, enemy.depth) {
+                continue;
+            }
+
+            commands.entity(bullet_entity).despawn_recursive();
+            enemy_hit_event_writer.send(EnemyHitEvent { player: player_id });
+            enemy.hp -= 1;
+            if enemy.hp == 0 {
+                commands.entity(enemy_entity).despawn_recursive();
+                score_event_writer.send(score::ScoreEvent {
+                    player: player_id,
+                    kind: score::ScoreKind::Kill(enemy.points),
+                });
+                if enemy.drops {
+                    drop_event_writer.send(pickup::PickupDropEvent {
+                        translation: enemy_transform.translation,
+                    });
+                }
+            }
+
+            break;
+        }
+    }
+}
+
+fn check_grazes(
+    players: Query<(&player::Player, &player::Moveable, &Transform)>,
+    mut enemies: Query<(&mut Enemy, &Transform)>,
+    mut score_event_writer: EventWriter<score::ScoreEvent>,
+) {
+    for (player, moveable, player_transform) in players.iter() {
+        for (mut enemy, enemy_transform) in enemies.iter_mut() {
+            if enemy.grazed || enemy_transform.translation.x > player_transform.translation.x {
+                continue;
+            }
+
+            // an enemy that slips past in a neighboring lane of the same row counts as a graze
+            enemy.grazed = true;
+            let distance = lane_distance(player_transform.translation, enemy_transform.translation);
+            if same_row(Some(moveable.depth()), enemy.depth) && distance > LANE_WIDTH && distance < GRAZE_WIDTH {
+                score_event_writer.send(score::ScoreEvent {
+                    player: player.id,
+                    kind: score::ScoreKind::Graze,
+                });
+            }
+        }
+    }
+}
+
+fn player_collisions(
+    mut commands: Commands,
+    players: Query<(&player::Player, &player::Moveable, &Transform)>,
+    enemies: Query<(Entity, &Enemy, &Transform)>,
+    bullets: Query<(Entity, &bullet::Bullet, &Transform)>,
+    mut player_hit_event_writer: EventWriter<player::PlayerHitEvent>,
+) {
+    for (player, moveable, player_transform) in players.iter() {
+        let depth = Some(moveable.depth());
+        for (entity, enemy, enemy_transform) in enemies.iter() {
+            if touching(player_transform.translation, depth, enemy_transform.translation, enemy.depth) {
+                commands.entity(entity).despawn_recursive();
+                player_hit_event_writer.send(player::PlayerHitEvent { player: player.id });
+            }
+        }
+
+        for (entity, bullet, bullet_transform) in bullets.iter() {
+            if bullet.owner == bullet::Owner::Enemy && touching(player_transform.translation, depth, bullet_transform.translation, bullet.depth) {
+                commands.entity(entity).despawn_recursive();
+                player_hit_event_writer.send(player::PlayerHitEvent { player: player.id });
+            }
+        }
+    }
+}
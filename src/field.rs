@@ -9,22 +9,25 @@ use bevy::{
         shader::ShaderStages,
     },
 };
-use crate::{asset_loader, player, };
+use crate::{asset_loader, attract, player, campaign, difficulty, replay, settings};
 
 static SCALE:f32 = 30.0;
-static SPEED:f32 = 0.005;
 
 pub struct LevelReady(pub bool);
+// everything spawned for a level or the menu background, despawned when it's left
+pub struct LevelEntity;
 pub struct FieldPlugin;
 impl Plugin for FieldPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
             .insert_resource(LevelReady(false))
             .init_resource::<GameMeshes>()
+            .add_event::<player::PlayerHitEvent>()
             .add_asset::<FieldMaterial>()
             .add_system_set(
                SystemSet::on_enter(crate::AppState::Loading)
-                         .with_system(load_assets.system())
+                         .with_system(load_assets.system().after("load_manifest"))
+                         .with_system(load_shapes.system())
             )
             .add_system_set(
                 SystemSet::on_enter(crate::AppState::InGame)
@@ -37,18 +40,38 @@ impl Plugin for FieldPlugin {
                 SystemSet::on_exit(crate::AppState::InGame)
                     .with_system(cleanup_environment.system())
             )
+            .add_system_set(
+                SystemSet::on_enter(crate::AppState::MainMenu)
+                    .with_system(load_menu_background.system().label("loading_menu"))
+                    .with_system(crate::camera::create_camera.system().after("loading_menu"))
+                    .with_system(set_menu_clear_color.system())
+            )
+            .add_system_set(
+                SystemSet::on_update(crate::AppState::MainMenu)
+                    .with_system(animate_shader.system())
+            )
+            .add_system_set(
+                SystemSet::on_exit(crate::AppState::MainMenu)
+                    .with_system(cleanup_environment.system())
+            )
             .add_system_set(
                SystemSet::on_update(crate::AppState::InGame)
-                    .with_system(player::player_input.system())
                     .with_system(animate_shader.system())
-                    .with_system(player::update_moveables.system())
+            )
+            .add_system_set_to_stage(replay::FIXED_UPDATE,
+               replay::on_tick()
+                    .with_system(player::player_input.system().label("player_input").after("tick_input"))
+                    .with_system(player::depth_input.system().after("player_input").before("movement"))
+                    .with_system(player::use_bomb.system().after("tick_input").before("collisions"))
+                    .with_system(player::update_moveables::<With<player::Player>>.system().label("movement").after("player_input"))
+                    .with_system(player::handle_player_hits.system().after("collisions"))
             );
     }
 }
 
 #[derive(RenderResources, Default, TypeUuid)]
 #[uuid = "3bf9e364-f29d-4d6c-92cf-93298466c620"]
-struct FieldMaterial {
+pub struct FieldMaterial {
     pub color: Color,
 }
 #[derive(RenderResources, Default, TypeUuid)]
@@ -62,17 +85,31 @@ struct FieldShaderSettings {
 #[derive(Default)]
 pub struct GameMeshes {
     pub field: Handle<Mesh>,
-    pub field_material: Handle<StandardMaterial>,
+    pub field_material: Handle<FieldMaterial>,
     pub field_pipeline: Handle<PipelineDescriptor>,
+    pub player: Handle<Mesh>,
+    pub enemy: Handle<Mesh>,
+    pub boss: Handle<Mesh>,
+    pub bullet: Handle<Mesh>,
+    pub pickup: Handle<Mesh>,
+    pub enemy_material: Handle<StandardMaterial>,
+    // enemies in the front and back depth rows are told apart by color
+    pub front_enemy_material: Handle<StandardMaterial>,
+    pub back_enemy_material: Handle<StandardMaterial>,
+    pub pickup_material: Handle<StandardMaterial>,
+    pub reticle: Handle<Mesh>,
+    pub reticle_material: Handle<StandardMaterial>,
 }
 
 fn load_assets(
 //    mut commands: Commands,
     asset_server: Res<AssetServer>,
+    game_assets: Res<asset_loader::GameAssets>,
     mut game_meshes: ResMut<GameMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<FieldMaterial>>,
     mut pipelines: ResMut<Assets<PipelineDescriptor>>,
     mut render_graph: ResMut<RenderGraph>,
-    mut loading: ResMut<asset_loader::AssetsLoading>,
 ) {
     asset_server.watch_for_changes().unwrap();
 
@@ -86,16 +123,31 @@ fn load_assets(
 //      ..Default::default()
 //  });
 
-    let vertex = asset_server.load::<Shader, _>("shaders/field.vert");
-    let fragment = asset_server.load::<Shader, _>("shaders/field.frag");
-    loading.asset_handles.push(vertex.clone_untyped());
-    loading.asset_handles.push(fragment.clone_untyped());
+    game_meshes.player = game_assets.model("ship");
+    game_meshes.enemy = game_assets.model("enemy");
+    game_meshes.boss = game_assets.model("boss");
+    game_meshes.bullet = game_assets.model("bullet");
+    game_meshes.pickup = game_assets.model("pickup");
+
+    let vertex = game_assets.shader("field_vert");
+    let fragment = game_assets.shader("field_frag");
     game_meshes.field_pipeline = pipelines.add(PipelineDescriptor::default_config(ShaderStages {
         vertex, 
         fragment: Some(fragment),
     }));
 
 
+    // shared by every field that gets spawned so restarting doesn't pile up copies
+    game_meshes.field = meshes.add(Mesh::from(shape::Plane { size: 100.0 }));
+    game_meshes.field_material = materials.add(FieldMaterial {
+        color: Color::rgb(0.0, 0.8, 0.0),
+    });
+
+    // adding a node name twice panics, so only add them the first time through
+    if render_graph.get_node_id("field_material").is_ok() {
+        return;
+    }
+
     render_graph.add_system_node(
         "field_material",
         AssetRenderResourcesNode::<FieldMaterial>::new(true),
@@ -110,50 +162,120 @@ fn load_assets(
         .unwrap();
 }
 
+// every spawn clones these instead of adding its own copy to the asset storage
+fn load_shapes(
+    mut game_meshes: ResMut<GameMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut standard_materials: ResMut<Assets<StandardMaterial>>,
+) {
+    game_meshes.enemy_material = standard_materials.add(Color::rgb(0.9, 0.1, 0.3).into());
+    game_meshes.pickup_material = standard_materials.add(Color::rgb(1.0, 0.85, 0.2).into());
+    game_meshes.front_enemy_material = standard_materials.add(Color::rgb(1.0, 0.6, 0.2).into());
+    game_meshes.back_enemy_material = standard_materials.add(Color::rgb(0.5, 0.1, 0.6).into());
+    game_meshes.reticle = meshes.add(Mesh::from(shape::Torus {
+        radius: 0.8,
+        ring_radius: 0.1,
+        ..Default::default()
+    }));
+    game_meshes.reticle_material = standard_materials.add(StandardMaterial {
+        base_color: Color::rgb(0.2, 1.0, 0.6),
+        unlit: true,
+        ..Default::default()
+    });
+}
+
 fn cleanup_environment(
+    mut commands: Commands,
+    mut level_ready: ResMut<LevelReady>,
+    level_entities: Query<Entity, With<LevelEntity>>,
 ) {
+    for entity in level_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    level_ready.0 = false;
 }
 
 struct Field;
 
-fn load_level( 
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<FieldMaterial>>,
-    mut level_ready: ResMut<LevelReady>,
-    game_meshes: Res<GameMeshes>,
+// the shader settings live on the field itself, so a fresh field starts its time back at zero
+fn spawn_field(
+    commands: &mut Commands,
+    game_meshes: &GameMeshes,
+    speed: f32,
 ) {
     let mut transform = Transform::identity();
     transform.apply_non_uniform_scale(Vec3::new(SCALE, 1.0, SCALE)); 
 
-    let material = materials.add(FieldMaterial {
-        color: Color::rgb(0.0, 0.8, 0.0),
-    });
-
     commands.spawn_bundle(PbrBundle {
                 transform,
-                mesh: meshes.add(Mesh::from(shape::Plane { size: 100.0 })),
+                mesh: game_meshes.field.clone(),
                 render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
                     game_meshes.field_pipeline.clone(),
                 )]),
                 ..Default::default()
             })
             .insert(Field {})
-            .insert(FieldShaderSettings { time: 0.0, speed: SPEED, scale: SCALE })
-            .insert(material);
+            .insert(LevelEntity)
+            .insert(FieldShaderSettings { time: 0.0, speed, scale: SCALE })
+            .insert(game_meshes.field_material.clone());
+}
 
-    player::spawn_player(&mut commands, &mut meshes);
+fn load_level( 
+    mut commands: Commands,
+    mut level_ready: ResMut<LevelReady>,
+    game_meshes: Res<GameMeshes>,
+    campaign: Res<campaign::Campaign>,
+    difficulty: Res<difficulty::Difficulty>,
+    settings: Res<settings::Settings>,
+    attract: Res<attract::AttractMode>,
+    playback: Res<replay::Playback>,
+) {
+    // a replay fires the way the run did and the demo pilot just holds fire
+    let (fire_mode, input_offset_ms, diagonal_window_ms, depth_lanes) = match &playback.replay {
+        Some(replay) => (replay.fire_mode, replay.input_offset_ms, replay.diagonal_window_ms, replay.depth_lanes),
+        None if attract.active => (player::FireMode::Hold, 0, 0, false),
+        None => (settings.fire_mode(player::LOCAL_PLAYER), settings.input_offset_ms, settings.diagonal_window_ms, settings.depth_lanes),
+    };
+
+    spawn_field(&mut commands, &game_meshes, campaign.current_stage().theme.speed);
+    let player = player::spawn_player(&mut commands, &game_meshes, player::LOCAL_PLAYER, difficulty.player_lives(), fire_mode, input_offset_ms, diagonal_window_ms);
+    if depth_lanes {
+        commands.entity(player).insert(player::DepthLanes);
+    }
+
+    level_ready.0 = true;
+}
+
+// the title screen scrolls the first stage's field behind the menu
+fn load_menu_background(
+    mut commands: Commands,
+    mut level_ready: ResMut<LevelReady>,
+    game_meshes: Res<GameMeshes>,
+    campaign: Res<campaign::Campaign>,
+) {
+    spawn_field(&mut commands, &game_meshes, campaign.stages[0].theme.speed);
 
     level_ready.0 = true;
 }
 
+// accumulated rather than read from the clock so the field stops scrolling while paused
 fn animate_shader(time: Res<Time>, mut query: Query<&mut FieldShaderSettings>) {
-    let shader_settings = query.single_mut();
-    shader_settings.unwrap().time = time.seconds_since_startup() as f32;
+    for mut shader_settings in query.iter_mut() {
+        shader_settings.time += time.delta_seconds();
+    }
 }
 
 fn set_clear_color(
     mut clear_color: ResMut<ClearColor>,
+    campaign: Res<campaign::Campaign>,
+) {
+    clear_color.0 = campaign.current_stage().theme.clear_color;
+}
+
+fn set_menu_clear_color(
+    mut clear_color: ResMut<ClearColor>,
+    campaign: Res<campaign::Campaign>,
 ) {
-    clear_color.0 = Color::hex("21123d").unwrap();
+    clear_color.0 = campaign.stages[0].theme.clear_color;
 }
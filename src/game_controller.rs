@@ -1,16 +1,40 @@
 use bevy::prelude::*;
+use std::collections::HashMap;
 
-pub struct GameController(Gamepad);
+use crate::input_map;
+
+pub struct GameController(pub Gamepad);
+
+// bevy's Connected event only carries an id, so names are looked up from gilrs afterwards
+#[derive(Default)]
+pub struct GamepadInfo {
+    pub names: HashMap<Gamepad, String>,
+    // stays the same for a device between connections, unlike the Gamepad id
+    pub identities: HashMap<Gamepad, String>,
+    unnamed: Vec<Gamepad>,
+}
+
+// gilrs' uuid when the platform has one, otherwise the name has to do
+fn identity(name: &str, uuid: [u8; 16]) -> String {
+    if uuid == [0; 16] {
+        format!("name:{}", name)
+    } else {
+        let hex: String = uuid.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("uuid:{}", hex)
+    }
+}
 
 pub fn gamepad_connections(
     mut commands: Commands,
     my_gamepad: Option<Res<GameController>>,
+    mut gamepad_info: ResMut<GamepadInfo>,
     mut gamepad_evr: EventReader<GamepadEvent>,
 ) {
     for GamepadEvent(id, kind) in gamepad_evr.iter() {
         match kind {
             GamepadEventType::Connected => {
                 println!("New gamepad connected with ID: {:?}", id);
+                gamepad_info.unnamed.push(*id);
 
                 // if we don't have any gamepad yet, use this one
                 if my_gamepad.is_none() {
@@ -19,6 +43,8 @@ pub fn gamepad_connections(
             }
             GamepadEventType::Disconnected => {
                 println!("Lost gamepad connection with ID: {:?}", id);
+                gamepad_info.names.remove(id);
+                gamepad_info.identities.remove(id);
 
                 // if it's the one we previously associated with the player,
                 // disassociate it:
@@ -34,108 +60,78 @@ pub fn gamepad_connections(
     }
 }
 
-#[derive(PartialEq)]
-pub enum GameButton {
-    Up, Down, Left, Right, Action, Nothing, Start
-}
-
-pub fn get_pressed_buttons(
-    axes: &Res<Axis<GamepadAxis>>,
-    buttons: &Res<Input<GamepadButton>>,
-    gamepad: Option<Res<GameController>>,
-) -> Vec::<GameButton> {
-    let mut pressed_buttons = vec!();
-    let gamepad = if let Some(gp) = gamepad {
-        // a gamepad is connected, we have the id
-        gp.0
-    } else {
-        // no gamepad is connected
-        return pressed_buttons;
+// bevy_gilrs keeps its Gilrs as a non-send resource and uses gilrs' ids for its Gamepads,
+// this has to be an exclusive system since there's no optional NonSend system parameter
+pub fn name_gamepads(world: &mut World) {
+    let unnamed = match world.get_resource::<GamepadInfo>() {
+        Some(gamepad_info) if !gamepad_info.unnamed.is_empty() => gamepad_info.unnamed.clone(),
+        _ => return,
     };
 
-    // The joysticks are represented using a separate axis for X and Y
-    let axis_lx = GamepadAxis(gamepad, GamepadAxisType::LeftStickX);
-    let axis_ly = GamepadAxis(gamepad, GamepadAxisType::LeftStickY);
-
-    if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
-        // combine X and Y into one vector
-        let left_stick_pos = Vec2::new(x, y);
-
-        // implement a dead-zone to ignore small inputs
-        if left_stick_pos.length() > 0.1 {
-            // do something with the position of the left stick
-            if x > 0.0 {
-                pressed_buttons.push(GameButton::Right);
-            }
-            if x < 0.0 {
-                pressed_buttons.push(GameButton::Left);
-            }
-            if y > 0.0 {
-                pressed_buttons.push(GameButton::Up);
-            }
-            if y < 0.0 {
-                pressed_buttons.push(GameButton::Down);
-            }
-        }
-    }
-
-    let axis_dx = GamepadAxis(gamepad, GamepadAxisType::DPadX);
-    let axis_dy = GamepadAxis(gamepad, GamepadAxisType::DPadY);
-
-    if let (Some(x), Some(y)) = (axes.get(axis_dx), axes.get(axis_dy)) {
-        // combine X and Y into one vector
-        let left_stick_pos = Vec2::new(x, y);
+    let names: Vec<(Gamepad, String, [u8; 16])> = match world.get_non_send_resource::<gilrs::Gilrs>() {
+        Some(gilrs) => gilrs.gamepads()
+                            .map(|(id, gamepad)| (Gamepad(id.into()), gamepad.name().to_string(), gamepad.uuid()))
+                            .filter(|(gamepad, _, _)| unnamed.contains(gamepad))
+                            .collect(),
+        None => vec!(),
+    };
 
-        // implement a dead-zone to ignore small inputs
-        if left_stick_pos.length() > 0.1 {
-            // do something with the position of the left stick
-            if x > 0.0 {
-                pressed_buttons.push(GameButton::Right);
-            }
-            if x < 0.0 {
-                pressed_buttons.push(GameButton::Left);
-            }
-            if y > 0.0 {
-                pressed_buttons.push(GameButton::Up);
-            }
-            if y < 0.0 {
-                pressed_buttons.push(GameButton::Down);
-            }
+    if let Some(mut gamepad_info) = world.get_resource_mut::<GamepadInfo>() {
+        for (gamepad, name, uuid) in names {
+            println!("Gamepad {:?} is a {}", gamepad, name);
+            gamepad_info.identities.insert(gamepad, identity(&name, uuid));
+            gamepad_info.names.insert(gamepad, name);
         }
+        gamepad_info.unnamed.clear();
     }
+}
 
-    let dpad_up = GamepadButton(gamepad, GamepadButtonType::DPadUp);
-    let dpad_down = GamepadButton(gamepad, GamepadButtonType::DPadDown);
-    let dpad_left = GamepadButton(gamepad, GamepadButtonType::DPadLeft);
-    let dpad_right = GamepadButton(gamepad, GamepadButtonType::DPadRight);
-
-    if buttons.pressed(dpad_up) {
-        pressed_buttons.push(GameButton::Up);
-    }
-
-    if buttons.pressed(dpad_down) {
-        pressed_buttons.push(GameButton::Down);
-    }
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameButton {
+    Up, Down, Left, Right, Action, Nothing, Start
+}
 
-    if buttons.pressed(dpad_left) {
-        pressed_buttons.push(GameButton::Left);
-    }
+// buttons that went down this frame, for navigating menus one step at a time
+#[derive(Default)]
+pub struct MenuInput {
+    pub just_pressed: Vec<GameButton>,
+}
 
-    if buttons.pressed(dpad_right) {
-        pressed_buttons.push(GameButton::Right);
+impl MenuInput {
+    pub fn pressed(&self, button: GameButton) -> bool {
+        self.just_pressed.contains(&button)
     }
 
-    let action_1 = GamepadButton(gamepad, GamepadButtonType::South);
-    let action_2 = GamepadButton(gamepad, GamepadButtonType::East);
-
-    if buttons.just_pressed(action_1) || buttons.just_pressed(action_2) {
-        pressed_buttons.push(GameButton::Action);
+    // a state change can run the next state's systems in the same frame, so whichever
+    // menu acts on a press consumes it before the next menu sees it too
+    pub fn consume(&mut self) {
+        self.just_pressed.clear();
     }
+}
 
-    let start_button = GamepadButton(gamepad, GamepadButtonType::Start);
-    if buttons.just_pressed(start_button) {
-        pressed_buttons.push(GameButton::Start);
-    }
+// actions fed in by something other than a player, like the attract mode pilot.
+// While active, player_input reads these instead of the InputMap.
+#[derive(Default)]
+pub struct PilotInput {
+    pub active: bool,
+    pub actions: Vec<input_map::Action>,
+}
 
-    pressed_buttons
+static MENU_BUTTONS: [(input_map::Action, GameButton); 6] = [
+    (input_map::Action::MoveUp, GameButton::Up),
+    (input_map::Action::MoveDown, GameButton::Down),
+    (input_map::Action::MoveLeft, GameButton::Left),
+    (input_map::Action::MoveRight, GameButton::Right),
+    (input_map::Action::Fire, GameButton::Action),
+    (input_map::Action::Pause, GameButton::Start),
+];
+
+pub fn update_menu_input(
+    input_map: Res<input_map::InputMap>,
+    mut menu_input: ResMut<MenuInput>,
+) {
+    menu_input.just_pressed = MENU_BUTTONS.iter()
+                                          .filter(|(action, _)| input_map.just_pressed(*action))
+                                          .map(|(_, button)| *button)
+                                          .collect();
 }
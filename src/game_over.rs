@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::{attract, difficulty, hud, player, stats, ui, game_controller::{GameButton, MenuInput}};
+
+static STARTING_CREDITS:u32 = 3;
+static CONTINUE_SECONDS:f32 = 9.0;
+static GAME_OVER_SECONDS:f32 = 3.0;
+
+pub struct Credits(pub u32);
+
+struct ContinueCountdown(Timer);
+struct GameOverTimer(Timer);
+
+pub struct GameOverPlugin;
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Credits(STARTING_CREDITS))
+           .insert_resource(ContinueCountdown(Timer::from_seconds(CONTINUE_SECONDS, false)))
+           .insert_resource(GameOverTimer(Timer::from_seconds(GAME_OVER_SECONDS, false)))
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(reset_credits.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(check_game_over.system().label("check_game_over"))
+           )
+
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Continue)
+                   .with_system(setup_continue.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Continue)
+                   .with_system(update_continue.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Continue)
+                   .with_system(ui::cleanup_screen.system())
+           )
+
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::GameOver)
+                   .with_system(setup_game_over.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::GameOver)
+                   .with_system(update_game_over.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::GameOver)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+fn reset_credits(
+    mut credits: ResMut<Credits>,
+) {
+    credits.0 = STARTING_CREDITS;
+}
+
+fn check_game_over(
+    mut state: ResMut<State<crate::AppState>>,
+    credits: Res<Credits>,
+    attract: Res<attract::AttractMode>,
+    mut stats: ResMut<stats::RunStats>,
+    players: Query<&player::PlayerStatus>,
+) {
+    if attract.active {
+        return;
+    }
+
+    if players.iter().next().is_none() || players.iter().any(|status| status.lives > 0) {
+        return;
+    }
+
+    // the level stays underneath the continue screen so it can pick up where it left off
+    let changed = if credits.0 > 0 {
+        state.push(crate::AppState::Continue)
+    } else {
+        state.set(crate::AppState::GameOver)
+    };
+
+    // another state change can already be queued this frame, this check runs again next frame
+    match changed {
+        Ok(()) => {
+            stats.deaths += 1;
+            stats.game_over = credits.0 == 0;
+        },
+        Err(_) => println!("Not ending the run yet, another state change is pending"),
+    }
+}
+
+fn setup_continue(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    mut countdown: ResMut<ContinueCountdown>,
+) {
+    countdown.0.reset();
+    ui::spawn_screen_text(&mut commands, &ui_font, 60.0);
+}
+
+fn update_continue(
+    mut state: ResMut<State<crate::AppState>>,
+    time: Res<Time>,
+    mut menu_input: ResMut<MenuInput>,
+    mut credits: ResMut<Credits>,
+    mut countdown: ResMut<ContinueCountdown>,
+    difficulty: Res<difficulty::Difficulty>,
+    mut stats: ResMut<stats::RunStats>,
+    mut players: Query<&mut player::PlayerStatus>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    if menu_input.pressed(GameButton::Action) {
+        credits.0 -= 1;
+        for mut status in players.iter_mut() {
+            status.lives = difficulty.player_lives();
+        }
+        menu_input.consume();
+        state.pop().unwrap();
+        return;
+    }
+
+    if countdown.0.tick(time.delta()).finished() || menu_input.pressed(GameButton::Start) {
+        // replacing the whole stack exits InGame too, which tears the level down
+        menu_input.consume();
+        state.replace(crate::AppState::GameOver).unwrap();
+        stats.game_over = true;
+        return;
+    }
+
+    let seconds_left = (countdown.0.duration() - countdown.0.elapsed()).as_secs_f32().ceil();
+    ui::set_screen_text(&mut texts, &format!("CONTINUE?\n{}\n\nCREDITS {}", seconds_left, credits.0));
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    mut game_over_timer: ResMut<GameOverTimer>,
+) {
+    game_over_timer.0.reset();
+    ui::spawn_screen_text(&mut commands, &ui_font, 80.0);
+}
+
+fn update_game_over(
+    mut state: ResMut<State<crate::AppState>>,
+    time: Res<Time>,
+    mut menu_input: ResMut<MenuInput>,
+    mut game_over_timer: ResMut<GameOverTimer>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    ui::set_screen_text(&mut texts, "GAME OVER");
+
+    if game_over_timer.0.tick(time.delta()).finished() || menu_input.pressed(GameButton::Action) {
+        menu_input.consume();
+        state.set(crate::AppState::Results).unwrap();
+    }
+}
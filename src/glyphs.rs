@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy::asset::FileAssetIo;
+use serde::Deserialize;
+use std::{collections::HashMap, fs};
+
+use crate::{game_controller, input_map::{self, Action, AxisDirection, Binding, InputMap}};
+
+static GLYPHS_FILE:&str = "glyphs.ron";
+static KEYBOARD_SET:&str = "keyboard";
+
+// see assets/glyphs.ron
+#[derive(Default, Deserialize)]
+struct GlyphFile {
+    devices: Vec<DeviceMatch>,
+    default_gamepad: String,
+    sets: HashMap<String, HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct DeviceMatch {
+    glyphs: String,
+    names: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputDevice {
+    Keyboard,
+    Gamepad,
+}
+
+// button prompts for whichever device the player touched last
+pub struct Glyphs {
+    file: GlyphFile,
+    device: InputDevice,
+    gamepad_set: String,
+    prompts: HashMap<Action, String>,
+    // what the prompts were made from, the InputMap changes every frame so is_changed doesn't help
+    bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs {
+            file: GlyphFile::default(),
+            device: InputDevice::Keyboard,
+            gamepad_set: String::new(),
+            prompts: HashMap::new(),
+            bindings: HashMap::new(),
+        }
+    }
+}
+
+impl Glyphs {
+    // something like "[A]" for the action on the current device, for menus to put in their text
+    pub fn prompt(&self, action: Action) -> &str {
+        self.prompts.get(&action).map_or("?", |prompt| prompt.as_str())
+    }
+
+    fn glyph_set_for(&self, gamepad_name: &str) -> String {
+        let gamepad_name = gamepad_name.to_lowercase();
+        self.file.devices.iter()
+                         .find(|device| device.names.iter().any(|name| gamepad_name.contains(name.as_str())))
+                         .map_or_else(|| self.file.default_gamepad.clone(), |device| device.glyphs.clone())
+    }
+
+    fn label(&self, set: &str, binding: &Binding) -> String {
+        let key = match binding {
+            Binding::Key(key_code) => format!("{:?}", key_code),
+            Binding::Button(button_type) => format!("{:?}", button_type),
+            Binding::Axis(axis_type, AxisDirection::Positive) => format!("{:?}+", axis_type),
+            Binding::Axis(axis_type, AxisDirection::Negative) => format!("{:?}-", axis_type),
+        };
+
+        self.file.sets.get(set)
+                      .and_then(|glyphs| glyphs.get(&key))
+                      .cloned()
+                      .unwrap_or_else(|| binding.name())
+    }
+
+    fn refresh(&mut self, input_map: &InputMap) {
+        self.bindings = input_map.bindings.clone();
+        let (set, keyboard) = match self.device {
+            InputDevice::Keyboard => (KEYBOARD_SET.to_string(), true),
+            InputDevice::Gamepad => (self.gamepad_set.clone(), false),
+        };
+
+        self.prompts = input_map::ACTIONS.iter().map(|action| {
+                                                let bindings = input_map.bindings(*action);
+                                                // a button reads better than a stick direction
+                                                let binding = bindings.iter()
+                                                                      .filter(|binding| binding.is_keyboard() == keyboard)
+                                                                      .min_by_key(|binding| matches!(binding, Binding::Axis(_, _)))
+                                                                      .or_else(|| bindings.first());
+                                                let prompt = binding.map_or_else(|| "?".to_string(), |binding| format!("[{}]", self.label(&set, binding)));
+                                                (*action, prompt)
+                                            })
+                                            .collect();
+    }
+}
+
+pub struct GlyphsPlugin;
+impl Plugin for GlyphsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Glyphs>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Loading)
+                   .with_system(load_glyphs.system())
+           )
+           .add_system(track_device.system());
+    }
+}
+
+fn load_glyphs(
+    mut glyphs: ResMut<Glyphs>,
+    input_map: Res<InputMap>,
+) {
+    let path = FileAssetIo::get_root_path().join("assets").join(GLYPHS_FILE);
+    let file = fs::read_to_string(&path)
+                  .map_err(|e| e.to_string())
+                  .and_then(|contents| ron::de::from_str::<GlyphFile>(&contents).map_err(|e| e.to_string()));
+
+    // prompts fall back to the bindings' names, so this isn't worth stopping for
+    match file {
+        Ok(file) => glyphs.file = file,
+        Err(e) => println!("Could not load {:?}: {}", path, e),
+    }
+
+    glyphs.gamepad_set = glyphs.file.default_gamepad.clone();
+    glyphs.refresh(&input_map);
+}
+
+// switches the prompts over as soon as the player uses the other kind of device
+fn track_device(
+    mut glyphs: ResMut<Glyphs>,
+    input_map: Res<InputMap>,
+    gamepad_info: Res<game_controller::GamepadInfo>,
+    keyboard_input: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad: Option<Res<game_controller::GameController>>,
+) {
+    let gamepad = gamepad.map(|gamepad| gamepad.0);
+    let stick_settings = &input_map.stick_settings;
+    let gamepad_used = match gamepad {
+        Some(gamepad) => buttons.get_just_pressed().any(|button| button.0 == gamepad)
+                         || input_map::axis_values(&axes, gamepad, stick_settings).values().any(|value| value.abs() > stick_settings.press_threshold),
+        None => false,
+    };
+
+    let device =
+        if keyboard_input.get_just_pressed().next().is_some() {
+            InputDevice::Keyboard
+        } else if gamepad_used {
+            InputDevice::Gamepad
+        } else {
+            glyphs.device
+        };
+
+    let gamepad_set = gamepad.and_then(|gamepad| gamepad_info.names.get(&gamepad))
+                             .map_or_else(|| glyphs.file.default_gamepad.clone(), |name| glyphs.glyph_set_for(name));
+
+    if device != glyphs.device || gamepad_set != glyphs.gamepad_set || input_map.bindings != glyphs.bindings {
+        glyphs.device = device;
+        glyphs.gamepad_set = gamepad_set;
+        glyphs.refresh(&input_map);
+    }
+}
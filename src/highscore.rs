@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{campaign, difficulty, glyphs, save, score, hud, ui, game_controller::{GameButton, MenuInput}, input_map::Action};
+
+static HIGH_SCORE_FILE:&str = "highscores.ron";
+static HIGH_SCORE_VERSION:u32 = 1;
+static TABLE_SIZE:usize = 10;
+static INITIALS_LENGTH:usize = 3;
+static LETTERS:&[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+
+// the campaign is the only way to play so far
+pub static MODE:&str = "arcade";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub initials: String,
+    pub score: u64,
+    // older tables didn't record this, so treat them as normal
+    #[serde(default)]
+    pub difficulty: difficulty::DifficultyLevel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    pub level: String,
+    pub mode: String,
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScoreTable {
+    fn new(level: &str, mode: &str) -> Self {
+        HighScoreTable {
+            level: level.to_string(),
+            mode: mode.to_string(),
+            entries: (1..=TABLE_SIZE).rev()
+                                     .map(|i| HighScoreEntry {
+                                         initials: "PEW".to_string(),
+                                         score: i as u64 * 1000,
+                                         difficulty: difficulty::DifficultyLevel::Normal,
+                                     })
+                                     .collect(),
+        }
+    }
+
+    pub fn qualifies(&self, score: u64) -> bool {
+        self.entries.len() < TABLE_SIZE
+        || self.entries.iter().any(|entry| score > entry.score)
+    }
+
+    fn insert(&mut self, entry: HighScoreEntry) {
+        let index = self.entries.iter()
+                                .position(|existing| entry.score > existing.score)
+                                .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+        self.entries.truncate(TABLE_SIZE);
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub tables: Vec<HighScoreTable>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        save::load(HIGH_SCORE_FILE, HIGH_SCORE_VERSION).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        save::save(HIGH_SCORE_FILE, HIGH_SCORE_VERSION, self);
+    }
+
+    pub fn table(&mut self, level: &str, mode: &str) -> &mut HighScoreTable {
+        let index = match self.tables.iter().position(|table| table.level == level && table.mode == mode) {
+            Some(index) => index,
+            None => {
+                self.tables.push(HighScoreTable::new(level, mode));
+                self.tables.len() - 1
+            }
+        };
+
+        &mut self.tables[index]
+    }
+}
+
+pub struct HighScorePlugin;
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(HighScores::load())
+           .init_resource::<InitialsEntry>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::HighScoreEntry)
+                   .with_system(setup_initials_entry.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::HighScoreEntry)
+                   .with_system(enter_initials.system().label("enter_initials"))
+                   .with_system(update_high_score_text.system().after("enter_initials"))
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::HighScoreEntry)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+#[derive(Default)]
+pub struct InitialsEntry {
+    // scores from the last run still waiting for initials, in player order
+    pending: Vec<(usize, u64)>,
+    letters: Vec<usize>,
+    cursor: usize,
+}
+
+impl InitialsEntry {
+    fn initials(&self) -> String {
+        self.letters.iter().map(|letter| LETTERS[*letter] as char).collect()
+    }
+}
+
+fn setup_initials_entry(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    final_tally: Res<score::FinalTally>,
+    campaign: Res<campaign::Campaign>,
+    mut high_scores: ResMut<HighScores>,
+    mut initials_entry: ResMut<InitialsEntry>,
+) {
+    let table = high_scores.table(&campaign.current_stage().id, MODE);
+    *initials_entry = InitialsEntry {
+        pending: final_tally.players.iter()
+                                    .filter(|(_, player_score)| table.qualifies(player_score.points))
+                                    .map(|(id, player_score)| (*id, player_score.points))
+                                    .collect(),
+        letters: vec![0; INITIALS_LENGTH],
+        cursor: 0,
+    };
+
+    ui::spawn_screen_text(&mut commands, &ui_font, 40.0);
+}
+
+fn enter_initials(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    campaign: Res<campaign::Campaign>,
+    difficulty: Res<difficulty::Difficulty>,
+    mut high_scores: ResMut<HighScores>,
+    mut initials_entry: ResMut<InitialsEntry>,
+) {
+    if initials_entry.pending.is_empty() {
+        // nobody made the table, so any button moves on
+        if menu_input.pressed(GameButton::Action) || menu_input.pressed(GameButton::Start) {
+            menu_input.consume();
+            state.set(crate::AppState::LevelSelect).unwrap();
+        }
+        return;
+    }
+
+    let cursor = initials_entry.cursor;
+    if menu_input.pressed(GameButton::Up) {
+        initials_entry.letters[cursor] = (initials_entry.letters[cursor] + 1) % LETTERS.len();
+    }
+    if menu_input.pressed(GameButton::Down) {
+        initials_entry.letters[cursor] = (initials_entry.letters[cursor] + LETTERS.len() - 1) % LETTERS.len();
+    }
+    if menu_input.pressed(GameButton::Left) && cursor > 0 {
+        initials_entry.cursor -= 1;
+    }
+    if menu_input.pressed(GameButton::Right) && cursor < INITIALS_LENGTH - 1 {
+        initials_entry.cursor += 1;
+    }
+
+    if menu_input.pressed(GameButton::Action) {
+        if cursor < INITIALS_LENGTH - 1 {
+            initials_entry.cursor += 1;
+        } else {
+            let (_, score) = initials_entry.pending.remove(0);
+            let initials = initials_entry.initials();
+            high_scores.table(&campaign.current_stage().id, MODE).insert(HighScoreEntry { initials, score, difficulty: difficulty.level });
+            high_scores.save();
+
+            initials_entry.letters = vec![0; INITIALS_LENGTH];
+            initials_entry.cursor = 0;
+        }
+    }
+}
+
+fn update_high_score_text(
+    campaign: Res<campaign::Campaign>,
+    mut high_scores: ResMut<HighScores>,
+    initials_entry: Res<InitialsEntry>,
+    glyphs: Res<glyphs::Glyphs>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    let mut value = "HIGH SCORES\n\n".to_string();
+    for (rank, entry) in high_scores.table(&campaign.current_stage().id, MODE).entries.iter().enumerate() {
+        value.push_str(&format!("{:>2}. {}  {:>10}  {}\n", rank + 1, entry.initials, entry.score, entry.difficulty.name()));
+    }
+
+    value.push('\n');
+    if let Some((player, score)) = initials_entry.pending.first() {
+        let initials: String = initials_entry.initials()
+                                             .chars()
+                                             .enumerate()
+                                             .map(|(i, letter)| {
+                                                 if i == initials_entry.cursor {
+                                                     format!("[{}]", letter)
+                                                 } else {
+                                                     format!(" {} ", letter)
+                                                 }
+                                             })
+                                             .collect();
+        value.push_str(&format!("PLAYER {} - {}\n{}", player + 1, score, initials));
+    } else {
+        value.push_str(&format!("PRESS {}", glyphs.prompt(Action::Fire)));
+    }
+
+    ui::set_screen_text(&mut texts, &value);
+}
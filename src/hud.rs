@@ -0,0 +1,273 @@
+use bevy::prelude::*;
+
+use crate::{achievements, asset_loader, enemy, player, score};
+
+static FONT_SIZE:f32 = 28.0;
+static MARGIN:f32 = 16.0;
+static BOSS_BAR_HEIGHT:f32 = 18.0;
+// the HUD is laid out for this window height and scaled from there
+static REFERENCE_HEIGHT:f32 = 720.0;
+static TOAST_SECONDS:f32 = 3.0;
+
+#[derive(Default)]
+pub struct UiFont(pub Handle<Font>);
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<UiFont>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Loading)
+                   .with_system(load_assets.system().label("load_ui_font").after("load_manifest"))
+           )
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(spawn_boss_bar.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(spawn_player_huds.system().label("spawn_player_huds"))
+                   .with_system(update_player_huds.system().after("spawn_player_huds"))
+                   .with_system(update_boss_bar.system())
+                   .with_system(layout_hud.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(cleanup_hud.system())
+           )
+           // unlocks can land on the way out of a stage, so toasts outlive the rest of the HUD
+           .add_system(show_achievement_toasts.system())
+           .add_system(expire_toasts.system());
+    }
+}
+
+struct Hud;
+struct PlayerHud(usize);
+struct BossBar;
+struct BossBarFill;
+struct Toast(Timer);
+
+fn load_assets(
+    game_assets: Res<asset_loader::GameAssets>,
+    mut ui_font: ResMut<UiFont>,
+) {
+    ui_font.0 = game_assets.font("ui");
+}
+
+fn corner(player: usize, margin: f32) -> Rect<Val> {
+    // player one gets the top left, then around the screen clockwise-ish
+    let (top, left) = match player % 4 {
+        0 => (true, true),
+        1 => (true, false),
+        2 => (false, true),
+        _ => (false, false),
+    };
+
+    Rect {
+        top: if top { Val::Px(margin) } else { Val::Undefined },
+        bottom: if top { Val::Undefined } else { Val::Px(margin) },
+        left: if left { Val::Px(margin) } else { Val::Undefined },
+        right: if left { Val::Undefined } else { Val::Px(margin) },
+    }
+}
+
+fn spawn_player_huds(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    players: Query<&player::Player>,
+    huds: Query<&PlayerHud>,
+) {
+    for player in players.iter() {
+        if huds.iter().any(|hud| hud.0 == player.id) {
+            continue;
+        }
+
+        let style = TextStyle {
+            font: ui_font.0.clone(),
+            font_size: FONT_SIZE,
+            color: Color::WHITE,
+        };
+        let alignment = TextAlignment {
+            horizontal: if player.id % 2 == 0 { HorizontalAlign::Left } else { HorizontalAlign::Right },
+            ..Default::default()
+        };
+
+        commands.spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: corner(player.id, MARGIN),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        sections: (0..5).map(|_| TextSection { value: "".to_string(), style: style.clone() })
+                                        .collect(),
+                        alignment,
+                    },
+                    ..Default::default()
+                })
+                .insert(Hud)
+                .insert(PlayerHud(player.id));
+    }
+}
+
+fn update_player_huds(
+    score: Res<score::Score>,
+    players: Query<(&player::Player, &player::PlayerStatus)>,
+    mut huds: Query<(&PlayerHud, &mut Text)>,
+) {
+    for (hud, mut text) in huds.iter_mut() {
+        let player_score = score.players.get(&hud.0).cloned().unwrap_or_default();
+        let status = players.iter()
+                            .find(|(player, _)| player.id == hud.0)
+                            .map(|(_, status)| status);
+
+        text.sections[0].value = format!("{}P {:>10}\n", hud.0 + 1, player_score.points);
+        text.sections[1].value = format!("x{}\n", player_score.multiplier);
+        if let Some(status) = status {
+            text.sections[2].value = format!("LIVES {}\n", status.lives);
+            text.sections[3].value = format!("WEAPON LV {}\n", status.weapon_level);
+            text.sections[4].value = format!("BOMBS {}", status.bombs);
+        }
+    }
+}
+
+fn spawn_boss_bar(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn_bundle(NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(MARGIN),
+                        left: Val::Percent(25.0),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Percent(50.0), Val::Px(BOSS_BAR_HEIGHT)),
+                    ..Default::default()
+                },
+                material: materials.add(Color::rgba(0.1, 0.1, 0.1, 0.8).into()),
+                ..Default::default()
+            })
+            .insert(Hud)
+            .insert(BossBar)
+            .with_children(|parent| {
+                parent.spawn_bundle(NodeBundle {
+                          style: Style {
+                              size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                              ..Default::default()
+                          },
+                          material: materials.add(Color::rgb(0.9, 0.1, 0.3).into()),
+                          ..Default::default()
+                      })
+                      .insert(BossBarFill);
+            });
+}
+
+fn update_boss_bar(
+    bosses: Query<(&enemy::Enemy, &enemy::Boss)>,
+    mut bars: Query<&mut Style, (With<BossBar>, Without<BossBarFill>)>,
+    mut fills: Query<&mut Style, (With<BossBarFill>, Without<BossBar>)>,
+) {
+    let boss = bosses.iter().next();
+    let display = if boss.is_some() { Display::Flex } else { Display::None };
+    for mut style in bars.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    if let Some((enemy, boss)) = boss {
+        let health = enemy.hp as f32 / boss.max_hp.max(1) as f32;
+        for mut style in fills.iter_mut() {
+            style.size.width = Val::Percent(health * 100.0);
+        }
+    }
+}
+
+fn layout_hud(
+    windows: Res<Windows>,
+    mut texts: Query<(&PlayerHud, &mut Text, &mut Style), Without<BossBar>>,
+    mut bars: Query<&mut Style, With<BossBar>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let scale = window.height() / REFERENCE_HEIGHT;
+
+    // only touch the components when the size actually changes so the UI isn't relaid out every frame
+    for (hud, mut text, mut style) in texts.iter_mut() {
+        if (text.sections[0].style.font_size - FONT_SIZE * scale).abs() > f32::EPSILON {
+            for section in text.sections.iter_mut() {
+                section.style.font_size = FONT_SIZE * scale;
+            }
+            style.position = corner(hud.0, MARGIN * scale);
+        }
+    }
+
+    for mut style in bars.iter_mut() {
+        if style.size.height != Val::Px(BOSS_BAR_HEIGHT * scale) {
+            style.size.height = Val::Px(BOSS_BAR_HEIGHT * scale);
+            style.position.top = Val::Px(MARGIN * scale);
+        }
+    }
+}
+
+fn cleanup_hud(
+    mut commands: Commands,
+    huds: Query<Entity, With<Hud>>,
+) {
+    for entity in huds.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn show_achievement_toasts(
+    mut commands: Commands,
+    ui_font: Res<UiFont>,
+    windows: Res<Windows>,
+    toasts: Query<&Toast>,
+    mut unlocked_event_reader: EventReader<achievements::AchievementUnlockedEvent>,
+) {
+    let scale = windows.get_primary().map_or(1.0, |window| window.height() / REFERENCE_HEIGHT);
+
+    // stack new toasts above any that are still showing
+    for (count, event) in (toasts.iter().count()..).zip(unlocked_event_reader.iter()) {
+        commands.spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            bottom: Val::Px((MARGIN + count as f32 * FONT_SIZE * 1.5) * scale),
+                            left: Val::Percent(35.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        format!("ACHIEVEMENT UNLOCKED: {}", event.name),
+                        TextStyle {
+                            font: ui_font.0.clone(),
+                            font_size: FONT_SIZE * scale,
+                            color: Color::rgb(1.0, 0.85, 0.2),
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(Toast(Timer::from_seconds(TOAST_SECONDS, false)));
+    }
+}
+
+fn expire_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut Toast)>,
+) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
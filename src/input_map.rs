@@ -0,0 +1,379 @@
+use bevy::prelude::*;
+use bevy::input::InputSystem;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::game_controller;
+
+// the axis pairs that make up a stick, their deadzone is radial so a diagonal doesn't leak into the other axis
+static STICKS: [(GamepadAxisType, GamepadAxisType); 3] = [
+    (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+    (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+    (GamepadAxisType::DPadX, GamepadAxisType::DPadY),
+];
+static TRIGGERS: [GamepadAxisType; 2] = [GamepadAxisType::LeftZ, GamepadAxisType::RightZ];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Bomb,
+    Pause,
+    Quit,
+    MoveForward,
+    MoveBack,
+}
+
+// new actions go at the end, replays store actions by their index in here
+pub static ACTIONS: [Action; 10] = [
+    Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight,
+    Action::Fire, Action::Bomb, Action::Pause, Action::Quit,
+    Action::MoveForward, Action::MoveBack,
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "MOVE UP",
+            Action::MoveDown => "MOVE DOWN",
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Fire => "FIRE",
+            Action::Bomb => "BOMB",
+            Action::Pause => "PAUSE",
+            Action::Quit => "QUIT",
+            Action::MoveForward => "MOVE FORWARD",
+            Action::MoveBack => "MOVE BACK",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    Axis(GamepadAxisType, AxisDirection),
+}
+
+impl Binding {
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Binding::Key(_))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key_code) => format!("{:?}", key_code),
+            Binding::Button(button_type) => format!("{:?}", button_type),
+            Binding::Axis(axis_type, AxisDirection::Positive) => format!("{:?}+", axis_type),
+            Binding::Axis(axis_type, AxisDirection::Negative) => format!("{:?}-", axis_type),
+        }.to_uppercase()
+    }
+}
+
+// how the gamepad's analog axes are turned into presses
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StickSettings {
+    // travel inside the inner deadzone is ignored and anything past the outer one counts as fully pushed
+    pub inner_deadzone: f32,
+    pub outer_deadzone: f32,
+    // within this many degrees of straight up, down, left or right a stick only pushes that one direction
+    pub snap_angle: f32,
+    // an axis binding is pressed past the press threshold and stays held until it drops under the release one
+    pub press_threshold: f32,
+    pub release_threshold: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        StickSettings {
+            inner_deadzone: 0.2,
+            outer_deadzone: 0.95,
+            snap_angle: 30.0,
+            press_threshold: 0.5,
+            release_threshold: 0.35,
+        }
+    }
+}
+
+impl StickSettings {
+    // the settings file can be edited by hand, this puts everything back in the ranges the controls screen allows
+    pub fn repair(&mut self) {
+        let defaults = StickSettings::default();
+        let valid = |value: f32, default: f32| if value.is_finite() { value } else { default };
+
+        self.inner_deadzone = valid(self.inner_deadzone, defaults.inner_deadzone).clamp(0.0, 0.9);
+        self.outer_deadzone = valid(self.outer_deadzone, defaults.outer_deadzone).clamp(self.inner_deadzone + 0.1, 1.0);
+        self.snap_angle = valid(self.snap_angle, defaults.snap_angle).clamp(0.0, 45.0);
+        self.press_threshold = valid(self.press_threshold, defaults.press_threshold).clamp(0.1, 0.95);
+        self.release_threshold = valid(self.release_threshold, defaults.release_threshold).clamp(0.05, self.press_threshold);
+    }
+
+    // applies the radial deadzone and the snapping to one stick
+    pub fn stick_value(&self, x: f32, y: f32) -> Vec2 {
+        let stick = Vec2::new(x, y);
+        let length = stick.length();
+        // a zero length stick can't be normalized, so this has to catch it even with a negative deadzone
+        if length <= self.inner_deadzone.max(0.0) {
+            return Vec2::ZERO;
+        }
+
+        let range = (self.outer_deadzone - self.inner_deadzone).max(f32::EPSILON);
+        let length = ((length - self.inner_deadzone) / range).min(1.0);
+
+        // 0 is straight along x and 90 straight along y
+        let angle = y.abs().atan2(x.abs()).to_degrees();
+        if angle <= self.snap_angle {
+            Vec2::new(length.copysign(x), 0.0)
+        } else if angle >= 90.0 - self.snap_angle {
+            Vec2::new(0.0, length.copysign(y))
+        } else {
+            stick.normalize() * length
+        }
+    }
+
+    pub fn trigger_value(&self, value: f32) -> f32 {
+        let range = (self.outer_deadzone - self.inner_deadzone).max(f32::EPSILON);
+        ((value.abs() - self.inner_deadzone) / range).clamp(0.0, 1.0).copysign(value)
+    }
+}
+
+// the gamepad half of an InputMap, saved for each gamepad that's been set up
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GamepadProfile {
+    // what the gamepad called itself, just to make the settings file readable
+    pub name: String,
+    pub bindings: HashMap<Action, Vec<Binding>>,
+    pub stick_settings: StickSettings,
+}
+
+impl GamepadProfile {
+    // profiles saved before an action existed get its default gamepad bindings
+    pub fn add_missing_bindings(&mut self) {
+        for (action, bindings) in InputMap::default().gamepad_profile("").bindings {
+            self.bindings.entry(action).or_insert(bindings);
+        }
+    }
+}
+
+// which inputs trigger each action, and what those actions are doing this frame.
+// Only the bindings and stick settings are saved, they're stored in the settings file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputMap {
+    pub bindings: HashMap<Action, Vec<Binding>>,
+    #[serde(default)]
+    pub stick_settings: StickSettings,
+    // axis bindings past their threshold last frame, for the hysteresis
+    #[serde(skip)]
+    held_axes: Vec<Binding>,
+    #[serde(skip)]
+    pressed: Vec<Action>,
+    #[serde(skip)]
+    just_pressed: Vec<Action>,
+    #[serde(skip)]
+    just_released: Vec<Action>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::*;
+        use AxisDirection::*;
+
+        let bindings = [
+            (Action::MoveUp, vec!(Key(KeyCode::W), Key(KeyCode::Up), Button(GamepadButtonType::DPadUp),
+                                  Axis(GamepadAxisType::LeftStickY, Positive), Axis(GamepadAxisType::DPadY, Positive))),
+            (Action::MoveDown, vec!(Key(KeyCode::S), Key(KeyCode::Down), Button(GamepadButtonType::DPadDown),
+                                    Axis(GamepadAxisType::LeftStickY, Negative), Axis(GamepadAxisType::DPadY, Negative))),
+            (Action::MoveLeft, vec!(Key(KeyCode::A), Key(KeyCode::Left), Button(GamepadButtonType::DPadLeft),
+                                    Axis(GamepadAxisType::LeftStickX, Negative), Axis(GamepadAxisType::DPadX, Negative))),
+            (Action::MoveRight, vec!(Key(KeyCode::D), Key(KeyCode::Right), Button(GamepadButtonType::DPadRight),
+                                     Axis(GamepadAxisType::LeftStickX, Positive), Axis(GamepadAxisType::DPadX, Positive))),
+            (Action::Fire, vec!(Key(KeyCode::Space), Key(KeyCode::Return), Key(KeyCode::J),
+                                Button(GamepadButtonType::South), Button(GamepadButtonType::East))),
+            (Action::Bomb, vec!(Key(KeyCode::K), Button(GamepadButtonType::West))),
+            (Action::Pause, vec!(Key(KeyCode::Tab), Button(GamepadButtonType::Start))),
+            (Action::Quit, vec!(Key(KeyCode::Q), Key(KeyCode::Escape))),
+            (Action::MoveForward, vec!(Key(KeyCode::R), Button(GamepadButtonType::RightTrigger))),
+            (Action::MoveBack, vec!(Key(KeyCode::F), Button(GamepadButtonType::LeftTrigger))),
+        ].iter().cloned().collect();
+
+        InputMap {
+            bindings,
+            stick_settings: StickSettings::default(),
+            held_axes: vec!(),
+            pressed: vec!(),
+            just_pressed: vec!(),
+            just_released: vec!(),
+        }
+    }
+}
+
+impl InputMap {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |bindings| bindings.as_slice())
+    }
+
+    // settings saved before an action existed get its default bindings
+    pub fn add_missing_bindings(&mut self) {
+        for (action, bindings) in InputMap::default().bindings {
+            self.bindings.entry(action).or_insert(bindings);
+        }
+    }
+
+    // a new binding replaces whatever the action had from the same kind of device
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|existing| existing.is_keyboard() != binding.is_keyboard());
+        bindings.push(binding);
+    }
+
+    pub fn gamepad_profile(&self, name: &str) -> GamepadProfile {
+        let bindings = self.bindings.iter()
+                                    .map(|(action, bindings)| {
+                                        (*action, bindings.iter().filter(|binding| !binding.is_keyboard()).copied().collect())
+                                    })
+                                    .collect();
+
+        GamepadProfile {
+            name: name.to_string(),
+            bindings,
+            stick_settings: self.stick_settings.clone(),
+        }
+    }
+
+    // swaps in a gamepad's bindings and stick settings, the keyboard bindings are kept
+    pub fn apply_gamepad_profile(&mut self, profile: &GamepadProfile) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|binding| binding.is_keyboard());
+        }
+        for (action, bindings) in profile.bindings.iter() {
+            self.bindings.entry(*action).or_default().extend(bindings.iter().copied());
+        }
+        self.stick_settings = profile.stick_settings.clone();
+    }
+
+    // anything already held stays held until it's released, so a rebind doesn't fire an action
+    fn update(&mut self, pressed: Vec<Action>) {
+        self.just_pressed = pressed.iter().filter(|action| !self.pressed.contains(action)).copied().collect();
+        self.just_released = self.pressed.iter().filter(|action| !pressed.contains(action)).copied().collect();
+        self.pressed = pressed;
+    }
+}
+
+pub struct InputMapPlugin;
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // bevy updates its own input resources in PreUpdate, and every system in
+        // Update should see the same action state, so this runs right after them
+        app.add_system_to_stage(CoreStage::PreUpdate, update_input_map.system().label("input_map").after(InputSystem))
+           .add_system_to_stage(CoreStage::PreUpdate, game_controller::update_menu_input.system().after("input_map"));
+    }
+}
+
+// every axis of the gamepad after its deadzones and snapping
+pub fn axis_values(
+    axes: &Axis<GamepadAxis>,
+    gamepad: Gamepad,
+    stick_settings: &StickSettings,
+) -> HashMap<GamepadAxisType, f32> {
+    let raw = |axis_type: GamepadAxisType| axes.get(GamepadAxis(gamepad, axis_type)).unwrap_or(0.0);
+
+    let mut values = HashMap::new();
+    for (x_axis, y_axis) in STICKS.iter() {
+        let stick = stick_settings.stick_value(raw(*x_axis), raw(*y_axis));
+        values.insert(*x_axis, stick.x);
+        values.insert(*y_axis, stick.y);
+    }
+    for axis_type in TRIGGERS.iter() {
+        values.insert(*axis_type, stick_settings.trigger_value(raw(*axis_type)));
+    }
+
+    values
+}
+
+fn axis_held(
+    binding: &Binding,
+    axis_values: &HashMap<GamepadAxisType, f32>,
+    input_map: &InputMap,
+) -> bool {
+    if let Binding::Axis(axis_type, direction) = binding {
+        let value = axis_values.get(axis_type).copied().unwrap_or(0.0);
+        let value = match direction {
+            AxisDirection::Positive => value,
+            AxisDirection::Negative => -value,
+        };
+
+        let stick_settings = &input_map.stick_settings;
+        if input_map.held_axes.contains(binding) {
+            value > stick_settings.release_threshold
+        } else {
+            value > stick_settings.press_threshold
+        }
+    } else {
+        false
+    }
+}
+
+fn binding_pressed(
+    binding: &Binding,
+    keyboard_input: &Input<KeyCode>,
+    buttons: &Input<GamepadButton>,
+    held_axes: &[Binding],
+    gamepad: Option<Gamepad>,
+) -> bool {
+    match (binding, gamepad) {
+        (Binding::Key(key_code), _) => keyboard_input.pressed(*key_code),
+        (Binding::Button(button_type), Some(gamepad)) => buttons.pressed(GamepadButton(gamepad, *button_type)),
+        (Binding::Axis(_, _), Some(_)) => held_axes.contains(binding),
+        _ => false,
+    }
+}
+
+fn update_input_map(
+    keyboard_input: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad: Option<Res<game_controller::GameController>>,
+    mut input_map: ResMut<InputMap>,
+) {
+    let gamepad = gamepad.map(|gamepad| gamepad.0);
+    let axis_values = gamepad.map(|gamepad| axis_values(&axes, gamepad, &input_map.stick_settings))
+                             .unwrap_or_default();
+    let held_axes: Vec<Binding> = input_map.bindings.values()
+                                           .flatten()
+                                           .filter(|binding| axis_held(binding, &axis_values, &input_map))
+                                           .copied()
+                                           .collect();
+
+    let pressed = ACTIONS.iter()
+                         .filter(|action| {
+                             input_map.bindings(**action)
+                                      .iter()
+                                      .any(|binding| binding_pressed(binding, &keyboard_input, &buttons, &held_axes, gamepad))
+                         })
+                         .copied()
+                         .collect();
+
+    input_map.held_axes = held_axes;
+    input_map.update(pressed);
+}
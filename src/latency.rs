@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use bevy::ecs::schedule::ShouldRun;
+use std::{collections::VecDeque, time::Instant};
+
+use crate::{glyphs, hud, player, replay, settings, ui, Direction, Position,
+            game_controller::{GameButton, MenuInput},
+            input_map::{Action, InputMap}};
+
+// how many of the latest moves and frames the numbers cover
+static MOVE_SAMPLES:usize = 20;
+static FRAME_SAMPLES:usize = 120;
+// player::Moveable caps its head start at half a move anyway
+pub static MAX_INPUT_OFFSET_MS:u32 = 50;
+
+// checked in the same order as player_input, so the last one held wins the same way
+static MOVES: [(Action, Direction); 4] = [
+    (Action::MoveUp, Direction::Up),
+    (Action::MoveDown, Direction::Down),
+    (Action::MoveLeft, Direction::Left),
+    (Action::MoveRight, Direction::Right),
+];
+
+static GRID: [[Position; 3]; 3] = [
+    [Position::TopLeft, Position::TopCenter, Position::TopRight],
+    [Position::Left, Position::Center, Position::Right],
+    [Position::BottomLeft, Position::BottomCenter, Position::BottomRight],
+];
+
+// times from a movement input to the tick update_moveables starts moving the ship
+#[derive(Default)]
+struct LatencyProbe {
+    // when the game saw the input, anything before that is down to the OS and the device
+    pending: Option<(Instant, Direction)>,
+    // the test ship was given a move this tick, update_moveables decides whether it starts
+    queued: bool,
+    moves: VecDeque<f32>,
+    frames: VecDeque<f32>,
+}
+
+impl LatencyProbe {
+    fn movement_started(&mut self) {
+        if let Some((pressed, _)) = self.pending.take() {
+            push_sample(&mut self.moves, pressed.elapsed().as_secs_f32() * 1000.0, MOVE_SAMPLES);
+        }
+    }
+
+    // the input didn't start a move of its own, so timing it would be wrong
+    fn discard(&mut self) {
+        self.pending = None;
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<f32>, value: f32, limit: usize) {
+    samples.push_back(value);
+    if samples.len() > limit {
+        samples.pop_front();
+    }
+}
+
+fn average(samples: &VecDeque<f32>) -> f32 {
+    if samples.is_empty() {
+        0.0
+    } else {
+        samples.iter().sum::<f32>() / samples.len() as f32
+    }
+}
+
+fn range(samples: &VecDeque<f32>) -> (f32, f32) {
+    samples.iter().fold((f32::MAX, 0.0), |(min, max), sample| (min.min(*sample), max.max(*sample)))
+}
+
+// stands in for the player on the latency screen, it has no mesh and the screen draws it as text
+struct TestShip;
+
+pub struct LatencyPlugin;
+impl Plugin for LatencyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LatencyProbe>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Latency)
+                   .with_system(setup_latency.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Latency)
+                   .with_system(mark_input.system())
+                   .with_system(record_frame_time.system())
+                   .with_system(latency_screen.system())
+           )
+           .add_system_set_to_stage(replay::FIXED_UPDATE,
+               SystemSet::new()
+                   .with_run_criteria(calibrating.system())
+                   .with_system(test_ship_input.system().label("test_ship_input"))
+                   .with_system(player::update_moveables::<With<TestShip>>.system().label("test_ship_movement").after("test_ship_input"))
+                   .with_system(check_test_ship.system().after("test_ship_movement"))
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Latency)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+// like replay::on_tick, but for the latency screen
+fn calibrating(state: Res<State<crate::AppState>>) -> ShouldRun {
+    if *state.current() == crate::AppState::Latency {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn setup_latency(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+    mut latency_probe: ResMut<LatencyProbe>,
+) {
+    *latency_probe = LatencyProbe::default();
+    ui::spawn_screen_text(&mut commands, &ui_font, 32.0);
+
+    // no offset, the point is to measure what the offset should be
+    let lane = player::lane_translation(&Position::Center);
+    commands.spawn()
+            .insert(Transform::from_translation(Vec3::new(0.0, lane.y, lane.x)))
+            .insert(player::Moveable::new(0))
+            .insert(TestShip)
+            .insert(ui::Screen);
+}
+
+fn mark_input(
+    input_map: Res<InputMap>,
+    mut latency_probe: ResMut<LatencyProbe>,
+) {
+    if latency_probe.pending.is_some() {
+        return;
+    }
+
+    if let Some((_, direction)) = MOVES.iter().find(|(action, _)| input_map.just_pressed(*action)) {
+        latency_probe.pending = Some((Instant::now(), *direction));
+    }
+}
+
+fn record_frame_time(
+    time: Res<Time>,
+    mut latency_probe: ResMut<LatencyProbe>,
+) {
+    let delta = time.delta_seconds() * 1000.0;
+    if delta > 0.0 {
+        push_sample(&mut latency_probe.frames, delta, FRAME_SAMPLES);
+    }
+}
+
+fn test_ship_input(
+    input_map: Res<InputMap>,
+    mut latency_probe: ResMut<LatencyProbe>,
+    mut ships: Query<&mut player::Moveable, With<TestShip>>,
+) {
+    let held = MOVES.iter()
+                    .rev()
+                    .find(|(action, _)| input_map.pressed(*action))
+                    .map(|(_, direction)| *direction);
+
+    for mut ship in ships.iter_mut() {
+        if !ship.is_stopped() {
+            // the ship is still busy with the last move, so this press can't start one
+            latency_probe.discard();
+            continue;
+        }
+
+        // a tap can be over before the tick runs
+        if let Some(direction) = held.or_else(|| latency_probe.pending.map(|(_, direction)| direction)) {
+            ship.queue(direction);
+            latency_probe.queued = true;
+        }
+    }
+}
+
+// a queued move either starts straight away or, off the edge of the grid, doesn't happen at all
+fn check_test_ship(
+    mut latency_probe: ResMut<LatencyProbe>,
+    ships: Query<&player::Moveable, With<TestShip>>,
+) {
+    if !latency_probe.queued {
+        return;
+    }
+    latency_probe.queued = false;
+
+    for ship in ships.iter() {
+        if ship.is_stopped() {
+            latency_probe.discard();
+        } else {
+            latency_probe.movement_started();
+        }
+    }
+}
+
+fn latency_screen(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut settings: ResMut<settings::Settings>,
+    latency_probe: Res<LatencyProbe>,
+    glyphs: Res<glyphs::Glyphs>,
+    ships: Query<&player::Moveable, With<TestShip>>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    let measured = average(&latency_probe.moves);
+    let suggested = (measured.round() as u32).min(MAX_INPUT_OFFSET_MS);
+
+    if menu_input.pressed(GameButton::Start) {
+        menu_input.consume();
+        state.pop().unwrap();
+        return;
+    }
+
+    if menu_input.pressed(GameButton::Action) && !latency_probe.moves.is_empty() {
+        menu_input.consume();
+        settings.input_offset_ms = suggested;
+    }
+
+    let ship = ships.iter().next().map(|ship| ship.position());
+    let grid: Vec<String> = GRID.iter().map(|row| {
+                                    let cells: Vec<&str> = row.iter().map(|position| if Some(*position) == ship { "O" } else { "." }).collect();
+                                    cells.join(" ")
+                                })
+                                .collect();
+
+    let mut value = format!("LATENCY\n\nMOVE THE SHIP TO MEASURE\n\n{}\n\n", grid.join("\n"));
+
+    if latency_probe.moves.is_empty() {
+        value.push_str("INPUT TO MOVE: NO MOVES YET\n\n");
+    } else {
+        let (best, worst) = range(&latency_probe.moves);
+        value.push_str(&format!("INPUT TO MOVE: {:.0} MS AVERAGE OVER {} MOVES\nBEST {:.0} MS, WORST {:.0} MS\n",
+                                measured, latency_probe.moves.len(), best, worst));
+    }
+
+    if !latency_probe.frames.is_empty() {
+        let frame_time = average(&latency_probe.frames);
+        let (fastest, slowest) = range(&latency_probe.frames);
+        value.push_str(&format!("FRAME TIME: {:.1} MS AVERAGE, {:.1} - {:.1} MS ({:.0} FPS)\n",
+                                frame_time, fastest, slowest, 1000.0 / frame_time));
+    }
+    value.push_str(&format!("INPUT OFFSET: {} MS\n\n", settings.input_offset_ms));
+
+    if !latency_probe.moves.is_empty() {
+        value.push_str(&format!("{} USE {} MS AS THE INPUT OFFSET\n", glyphs.prompt(Action::Fire), suggested));
+    }
+    value.push_str(&format!("{} BACK", glyphs.prompt(Action::Pause)));
+
+    ui::set_screen_text(&mut texts, &value);
+}
@@ -5,18 +5,22 @@ use bevy::{prelude::*,};
 use bevy::DefaultPlugins;
 use bevy::app::AppExit;
 use bevy::app::Events;
-use bevy::window::WindowMode;
 
 fn main() {
+    // msaa and the window have to be set up before the plugins are added
+    let settings = settings::Settings::load();
+
     let mut app = App::build();
     app
-//      .insert_resource(Msaa { samples: 1 })
-//      .insert_resource(WindowDescriptor {
-//          width: 800.,
-//          height: 600.,
-//          title: "pewpewpew".to_string(), // ToDo
-//          ..Default::default()
-//      })
+        .insert_resource(Msaa { samples: settings.msaa_samples })
+        .insert_resource(WindowDescriptor {
+            width: settings.resolution.0 as f32,
+            height: settings.resolution.1 as f32,
+            title: "pewpewpew".to_string(),
+            ..Default::default()
+        })
+        .insert_resource(settings.input_map.clone())
+        .insert_resource(settings)
         .add_plugin(GamePlugin);
 
     app.run();
@@ -28,12 +32,52 @@ pub mod asset_loader;
 pub mod game_controller;
 pub mod player;
 pub mod bullet;
+pub mod enemy;
+pub mod score;
+pub mod pickup;
+pub mod save;
+pub mod highscore;
+pub mod hud;
+pub mod ui;
+pub mod game_over;
+pub mod results;
+pub mod campaign;
+pub mod sound;
+pub mod difficulty;
+pub mod achievements;
+pub mod stats;
+pub mod main_menu;
+pub mod options;
+pub mod pause;
+pub mod settings;
+pub mod attract;
+pub mod input_map;
+pub mod controls;
+pub mod replay;
+pub mod aim;
+pub mod glyphs;
+pub mod profiles;
+pub mod latency;
 mod field; 
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
     Loading,
+    AssetError,
+    MainMenu,
+    Options,
+    Controls,
+    GamepadSetup,
+    Latency,
+    Credits,
+    LevelSelect,
     InGame,
+    Paused,
+    Continue,
+    GameOver,
+    Results,
+    HighScoreEntry,
+    Achievements,
 }
 
 pub struct GamePlugin;
@@ -43,24 +87,59 @@ impl Plugin for GamePlugin {
         app.add_plugins(DefaultPlugins)
            .add_state(AppState::Loading)
 
-           .add_system_set(SystemSet::on_update(AppState::Loading)
-                   .with_system(asset_loader::check_assets_ready.system())
-           )
            .add_system_set(SystemSet::on_exit(AppState::Loading)
                    .with_system(fullscreen_app.system())
            )
 
+           // adds the fixed gameplay stage the other plugins put their systems in
+           .add_plugin(replay::ReplayPlugin)
+           .add_plugin(asset_loader::AssetLoaderPlugin)
            .add_plugin(field::FieldPlugin)
            .add_plugin(camera::CameraPlugin)
            .add_plugin(bullet::BulletPlugin)
-           .init_resource::<asset_loader::AssetsLoading>()
+           .add_plugin(enemy::EnemyPlugin)
+           .add_plugin(score::ScorePlugin)
+           .add_plugin(pickup::PickupPlugin)
+           .add_plugin(highscore::HighScorePlugin)
+           .add_plugin(hud::HudPlugin)
+           .add_plugin(game_over::GameOverPlugin)
+           .add_plugin(results::ResultsPlugin)
+           .add_plugin(campaign::CampaignPlugin)
+           .add_plugin(sound::SoundPlugin)
+           .add_plugin(achievements::AchievementsPlugin)
+           .add_plugin(stats::StatsPlugin)
+           .add_plugin(main_menu::MainMenuPlugin)
+           .add_plugin(options::OptionsPlugin)
+           .add_plugin(pause::PausePlugin)
+           .add_plugin(attract::AttractPlugin)
+           .add_plugin(input_map::InputMapPlugin)
+           .add_plugin(controls::ControlsPlugin)
+           .add_plugin(aim::AimPlugin)
+           .add_plugin(glyphs::GlyphsPlugin)
+           .add_plugin(profiles::ProfilesPlugin)
+           .add_plugin(latency::LatencyPlugin)
+           .init_resource::<difficulty::Difficulty>()
+           .init_resource::<game_controller::MenuInput>()
+           .add_startup_system(spawn_ui_camera.system())
+           .init_resource::<game_controller::GamepadInfo>()
+           .add_system(game_controller::gamepad_connections.system())
+           .add_system(game_controller::name_gamepads.exclusive_system())
            .add_system(debug_print_entity.system())
            .add_system(exit.system());
     }
 }
 
-fn exit(keys: Res<Input<KeyCode>>, mut exit: ResMut<Events<AppExit>>) {
-    if keys.just_pressed(KeyCode::Q) || keys.just_pressed(KeyCode::Escape) {
+fn exit(
+    input_map: Res<input_map::InputMap>,
+    state: Res<State<AppState>>,
+    mut exit: ResMut<Events<AppExit>>,
+) {
+    // the controls screen needs every key free to bind
+    if *state.current() == AppState::Controls {
+        return;
+    }
+
+    if input_map.just_pressed(input_map::Action::Quit) {
         exit.send(AppExit);
     }
 }
@@ -68,9 +147,35 @@ fn exit(keys: Res<Input<KeyCode>>, mut exit: ResMut<Events<AppExit>>) {
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Direction {
     Up, Down, Left, Right, 
+    UpLeft, UpRight, DownLeft, DownRight,
+    // along the x axis, between the depth rows
+    Forward, Back,
+}
+
+impl Direction {
+    // the diagonal made of an up or down and a left or right
+    pub fn diagonal(vertical: Direction, horizontal: Direction) -> Direction {
+        match (vertical, horizontal) {
+            (Direction::Up, Direction::Left) => Direction::UpLeft,
+            (Direction::Up, _) => Direction::UpRight,
+            (_, Direction::Left) => Direction::DownLeft,
+            _ => Direction::DownRight,
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        matches!(self, Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight)
+    }
 }
 
 #[derive(PartialEq, Clone, Copy, Hash, std::cmp::Eq)]
+pub enum Depth {
+    Back,
+    Middle,
+    Front,
+}
+
+#[derive(PartialEq, Clone, Copy, Hash, std::cmp::Eq, serde::Deserialize)]
 pub enum Position {
     TopCenter,
     BottomCenter,
@@ -85,13 +190,22 @@ pub enum Position {
     Right,
 }
 
+fn spawn_ui_camera(
+    mut commands: Commands,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
 pub fn fullscreen_app(
     mut windows: ResMut<Windows>,
+    settings: Res<settings::Settings>,
 ) {
     let window = windows.get_primary_mut().unwrap();
-    println!("Setting fullscreen...");
-    window.set_maximized(true);
-    window.set_mode(WindowMode::BorderlessFullscreen);
+    println!("Setting window mode to {}...", settings.window_mode.name());
+    if settings.window_mode == settings::WindowModeSetting::Borderless {
+        window.set_maximized(true);
+    }
+    window.set_mode(settings.window_mode());
 }
 
 pub fn debug_print_entity(
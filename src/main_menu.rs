@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+
+use crate::{attract, campaign, glyphs, hud, replay, ui, game_controller::{GameButton, MenuInput}, input_map::Action};
+
+static ITEMS: [&str; 7] = ["START", "CONTINUE", "LEVEL SELECT", "REPLAY", "OPTIONS", "CREDITS", "QUIT"];
+static CREDITS: &str = "PEWPEWPEW\n\n\
+                        DESIGN AND PROGRAMMING\nMRAMIREZ\n\n\
+                        FONT\nFIRA SANS BY MOZILLA\n\n\
+                        BUILT WITH BEVY";
+
+#[derive(Default)]
+struct MainMenuSelection(usize);
+
+pub struct MainMenuPlugin;
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MainMenuSelection>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::MainMenu)
+                   .with_system(setup_menu.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::MainMenu)
+                   .with_system(main_menu.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::MainMenu)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           // options is pushed over the menu, so the background stays up
+           .add_system_set(
+               SystemSet::on_pause(crate::AppState::MainMenu)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           .add_system_set(
+               SystemSet::on_resume(crate::AppState::MainMenu)
+                   .with_system(setup_menu.system())
+           )
+
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Credits)
+                   .with_system(setup_menu.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Credits)
+                   .with_system(credits.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Credits)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+fn setup_menu(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 48.0);
+}
+
+fn main_menu(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut selection: ResMut<MainMenuSelection>,
+    mut campaign: ResMut<campaign::Campaign>,
+    progress: Res<campaign::CampaignProgress>,
+    mut attract: ResMut<attract::AttractMode>,
+    mut playback: ResMut<replay::Playback>,
+    glyphs: Res<glyphs::Glyphs>,
+    mut app_exit: EventWriter<AppExit>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    selection.0 = ui::navigate_menu(&menu_input, selection.0, ITEMS.len());
+    let mut value = ui::menu_text("PEWPEWPEW", &ITEMS, selection.0);
+    value.push_str(&format!("\nPRESS {} TO SELECT", glyphs.prompt(Action::Fire)));
+    ui::set_screen_text(&mut texts, &value);
+
+    if !menu_input.pressed(GameButton::Action) {
+        return;
+    }
+    menu_input.consume();
+
+    match ITEMS[selection.0] {
+        "START" => {
+            campaign.current = 0;
+            state.set(crate::AppState::InGame).unwrap();
+        },
+        "CONTINUE" => {
+            // pick up from the furthest stage that's been unlocked
+            campaign.current = campaign.stages.iter()
+                                              .rposition(|stage| progress.is_unlocked(stage))
+                                              .unwrap_or(0);
+            state.set(crate::AppState::InGame).unwrap();
+        },
+        "LEVEL SELECT" => state.set(crate::AppState::LevelSelect).unwrap(),
+        "REPLAY" => {
+            // the last stage played, watched like the attract mode's demo
+            let replay = match replay::Replay::load() {
+                Some(replay) => replay,
+                None => {
+                    println!("No replay to play");
+                    return;
+                }
+            };
+
+            match campaign.stages.iter().position(|stage| stage.id == replay.stage) {
+                Some(stage) => {
+                    campaign.current = stage;
+                    attract.active = true;
+                    playback.replay = Some(replay);
+                    state.set(crate::AppState::InGame).unwrap();
+                },
+                None => println!("Replay is for an unknown stage {}", replay.stage),
+            }
+        },
+        "OPTIONS" => state.push(crate::AppState::Options).unwrap(),
+        "CREDITS" => state.set(crate::AppState::Credits).unwrap(),
+        _ => app_exit.send(AppExit),
+    }
+}
+
+fn credits(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    glyphs: Res<glyphs::Glyphs>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    ui::set_screen_text(&mut texts, &format!("{}\n\nPRESS {}", CREDITS, glyphs.prompt(Action::Fire)));
+
+    if menu_input.pressed(GameButton::Action) || menu_input.pressed(GameButton::Start) {
+        menu_input.consume();
+        state.set(crate::AppState::MainMenu).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+
+use crate::{difficulty, hud, latency, player, settings, ui, game_controller::{GameButton, MenuInput}};
+
+static VOLUME_STEP:f32 = 0.1;
+static INPUT_OFFSET_STEP:i32 = 5;
+static DIAGONAL_WINDOW_STEP:i32 = 10;
+static MAX_DIAGONAL_WINDOW_MS:i32 = 150;
+
+#[derive(Default)]
+struct OptionsSelection(usize);
+
+pub struct OptionsPlugin;
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<OptionsSelection>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Options)
+                   .with_system(setup_options.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Options)
+                   .with_system(options_menu.system().label("options_menu"))
+                   .with_system(apply_window_settings.system().after("options_menu"))
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Options)
+                   .with_system(save_settings.system())
+                   .with_system(ui::cleanup_screen.system())
+           )
+           // the controls and latency screens are pushed over this one
+           .add_system_set(
+               SystemSet::on_pause(crate::AppState::Options)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           .add_system_set(
+               SystemSet::on_resume(crate::AppState::Options)
+                   .with_system(setup_options.system())
+           );
+    }
+}
+
+fn setup_options(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 26.0);
+}
+
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, step: i32) -> T {
+    let index = options.iter().position(|option| *option == current).unwrap_or(0) as i32;
+    let count = options.len() as i32;
+    options[((index + step + count) % count) as usize]
+}
+
+// snapped to whole steps so repeated presses don't drift
+fn adjust_volume(volume: f32, step: i32) -> f32 {
+    ((volume / VOLUME_STEP).round() + step as f32).clamp(0.0, 1.0 / VOLUME_STEP) * VOLUME_STEP
+}
+
+fn volume_text(volume: f32) -> String {
+    format!("{}%", (volume * 100.0).round() as u32)
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "ON" } else { "OFF" }
+}
+
+enum OptionPress {
+    Open(crate::AppState),
+    Back,
+}
+
+// one line of the menu, what left and right do to it and what pressing it does
+struct OptionRow {
+    label: fn(&settings::Settings, &difficulty::Difficulty) -> String,
+    on_step: Option<fn(&mut settings::Settings, &mut difficulty::Difficulty, i32)>,
+    on_press: Option<OptionPress>,
+}
+
+static ROWS: [OptionRow; 17] = [
+    OptionRow {
+        label: |_, difficulty| format!("DIFFICULTY < {} >", difficulty.level.name()),
+        on_step: Some(|_, difficulty, step| {
+            let level = if step < 0 { difficulty.level.previous() } else { difficulty.level.next() };
+            difficulty.set_level(level);
+        }),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("WINDOW MODE < {} >", settings.window_mode.name()),
+        on_step: Some(|settings, _, step| {
+            settings.window_mode = if step < 0 { settings.window_mode.previous() } else { settings.window_mode.next() };
+        }),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("RESOLUTION < {}X{} >", settings.resolution.0, settings.resolution.1),
+        on_step: Some(|settings, _, step| settings.resolution = cycle(&settings::RESOLUTIONS, settings.resolution, step)),
+        on_press: None,
+    },
+    // the render pipelines are built with the sample count at startup
+    OptionRow {
+        label: |settings, _| format!("MSAA < {}X > (ON RESTART)", settings.msaa_samples),
+        on_step: Some(|settings, _, step| settings.msaa_samples = cycle(&settings::MSAA_SAMPLES, settings.msaa_samples, step)),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("MASTER VOLUME < {} >", volume_text(settings.master_volume)),
+        on_step: Some(|settings, _, step| settings.master_volume = adjust_volume(settings.master_volume, step)),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("MUSIC VOLUME < {} >", volume_text(settings.music_volume)),
+        on_step: Some(|settings, _, step| settings.music_volume = adjust_volume(settings.music_volume, step)),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("SFX VOLUME < {} >", volume_text(settings.sfx_volume)),
+        on_step: Some(|settings, _, step| settings.sfx_volume = adjust_volume(settings.sfx_volume, step)),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("SCREEN SHAKE < {} >", on_off(settings.screen_shake)),
+        on_step: Some(|settings, _, _| settings.screen_shake = !settings.screen_shake),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("LANGUAGE < {} >", settings.language_name()),
+        on_step: Some(|settings, _, step| {
+            let codes: Vec<&str> = settings::LANGUAGES.iter().map(|(code, _)| *code).collect();
+            settings.language = cycle(&codes, settings.language.as_str(), step).to_string();
+        }),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("AIMING < {} >", on_off(settings.aim_mode)),
+        on_step: Some(|settings, _, _| settings.aim_mode = !settings.aim_mode),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("AIM ASSIST < {} >", on_off(settings.aim_assist)),
+        on_step: Some(|settings, _, _| settings.aim_assist = !settings.aim_assist),
+        on_press: None,
+    },
+    // takes effect from the next stage, only the local player plays for now so only they get a row
+    OptionRow {
+        label: |settings, _| format!("P1 FIRE MODE < {} >", settings.fire_mode(player::LOCAL_PLAYER).name()),
+        on_step: Some(|settings, _, step| {
+            let fire_mode = settings.fire_mode(player::LOCAL_PLAYER);
+            let fire_mode = if step < 0 { fire_mode.previous() } else { fire_mode.next() };
+            settings.fire_modes.insert(player::LOCAL_PLAYER, fire_mode);
+        }),
+        on_press: None,
+    },
+    // pressing it opens the latency screen, which can measure it
+    OptionRow {
+        label: |settings, _| format!("INPUT OFFSET < {} MS >", settings.input_offset_ms),
+        on_step: Some(|settings, _, step| {
+            let offset = settings.input_offset_ms as i32 + step * INPUT_OFFSET_STEP;
+            settings.input_offset_ms = offset.clamp(0, latency::MAX_INPUT_OFFSET_MS as i32) as u32;
+        }),
+        on_press: Some(OptionPress::Open(crate::AppState::Latency)),
+    },
+    OptionRow {
+        label: |settings, _| format!("DIAGONAL WINDOW < {} MS >", settings.diagonal_window_ms),
+        on_step: Some(|settings, _, step| {
+            let window = settings.diagonal_window_ms as i32 + step * DIAGONAL_WINDOW_STEP;
+            settings.diagonal_window_ms = window.clamp(0, MAX_DIAGONAL_WINDOW_MS) as u32;
+        }),
+        on_press: None,
+    },
+    OptionRow {
+        label: |settings, _| format!("DEPTH LANES < {} >", on_off(settings.depth_lanes)),
+        on_step: Some(|settings, _, _| settings.depth_lanes = !settings.depth_lanes),
+        on_press: None,
+    },
+    OptionRow {
+        label: |_, _| "CONTROLS".to_string(),
+        on_step: None,
+        on_press: Some(OptionPress::Open(crate::AppState::Controls)),
+    },
+    // both the main menu and the pause menu push options, and wait underneath
+    OptionRow {
+        label: |_, _| "BACK".to_string(),
+        on_step: None,
+        on_press: Some(OptionPress::Back),
+    },
+];
+
+fn options_menu(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut selection: ResMut<OptionsSelection>,
+    mut difficulty: ResMut<difficulty::Difficulty>,
+    mut settings: ResMut<settings::Settings>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    selection.0 = ui::navigate_menu(&menu_input, selection.0, ROWS.len());
+    let row = &ROWS[selection.0];
+
+    let step = ui::menu_step(&menu_input);
+    if step != 0 {
+        if let Some(on_step) = row.on_step {
+            on_step(&mut settings, &mut difficulty, step);
+        }
+    }
+
+    if menu_input.pressed(GameButton::Action) {
+        if let Some(on_press) = &row.on_press {
+            menu_input.consume();
+            match on_press {
+                OptionPress::Open(screen) => state.push(screen.clone()).unwrap(),
+                OptionPress::Back => state.pop().unwrap(),
+            }
+            return;
+        }
+    }
+
+    let items: Vec<String> = ROWS.iter().map(|row| (row.label)(&settings, &difficulty)).collect();
+    let items: Vec<&str> = items.iter().map(|item| item.as_str()).collect();
+    ui::set_screen_text(&mut texts, &ui::menu_text("OPTIONS", &items, selection.0));
+}
+
+fn apply_window_settings(
+    mut windows: ResMut<Windows>,
+    settings: Res<settings::Settings>,
+) {
+    // only send window commands for what actually changed, switching modes can flicker
+    if let Some(window) = windows.get_primary_mut() {
+        let (width, height) = (settings.resolution.0 as f32, settings.resolution.1 as f32);
+        if window.requested_width() != width || window.requested_height() != height {
+            window.set_resolution(width, height);
+        }
+        if window.mode() != settings.window_mode() {
+            window.set_mode(settings.window_mode());
+        }
+    }
+}
+
+fn save_settings(
+    settings: Res<settings::Settings>,
+) {
+    settings.save();
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+
+use crate::{attract, hud, ui, game_controller::{GameButton, MenuInput}};
+
+static ITEMS: [&str; 4] = ["RESUME", "RESTART", "OPTIONS", "QUIT TO TITLE"];
+
+#[derive(Default)]
+struct PauseSelection(usize);
+
+// Paused is pushed on top of InGame, so everything registered for InGame's update
+// stops running until it's popped again while the level itself stays loaded
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PauseSelection>()
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(pause_game.system().after("check_game_over"))
+           )
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Paused)
+                   .with_system(reset_selection.system())
+                   .with_system(setup_pause.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Paused)
+                   .with_system(pause_menu.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Paused)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           // options is pushed over the pause menu, so hide it until options pops back
+           .add_system_set(
+               SystemSet::on_pause(crate::AppState::Paused)
+                   .with_system(ui::cleanup_screen.system())
+           )
+           .add_system_set(
+               SystemSet::on_resume(crate::AppState::Paused)
+                   .with_system(setup_pause.system())
+           );
+    }
+}
+
+fn pause_game(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    attract: Res<attract::AttractMode>,
+    mut window_focused_event_reader: EventReader<WindowFocused>,
+) {
+    // the demo ends on any button instead
+    if attract.active {
+        return;
+    }
+
+    let lost_focus = window_focused_event_reader.iter().any(|event| !event.focused);
+    if menu_input.pressed(GameButton::Start) || lost_focus {
+        menu_input.consume();
+
+        // a game over may have already queued the continue screen this frame
+        if state.push(crate::AppState::Paused).is_err() {
+            println!("Not pausing, another state change is pending");
+        }
+    }
+}
+
+fn reset_selection(
+    mut selection: ResMut<PauseSelection>,
+) {
+    selection.0 = 0;
+}
+
+fn setup_pause(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 48.0);
+}
+
+fn pause_menu(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    mut selection: ResMut<PauseSelection>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    selection.0 = ui::navigate_menu(&menu_input, selection.0, ITEMS.len());
+    ui::set_screen_text(&mut texts, &ui::menu_text("PAUSED", &ITEMS, selection.0));
+
+    if menu_input.pressed(GameButton::Start) {
+        menu_input.consume();
+        state.pop().unwrap();
+        return;
+    }
+
+    if !menu_input.pressed(GameButton::Action) {
+        return;
+    }
+    menu_input.consume();
+
+    // replacing the whole stack exits InGame, which tears the level down
+    match ITEMS[selection.0] {
+        "RESUME" => state.pop().unwrap(),
+        "RESTART" => state.replace(crate::AppState::InGame).unwrap(),
+        "OPTIONS" => state.push(crate::AppState::Options).unwrap(),
+        _ => state.replace(crate::AppState::MainMenu).unwrap(),
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use crate::{enemy, field, player, replay, score};
+
+static SPEED:f32 = 20.0;
+static DESPAWN_POINT:f32 = -20.0;
+static PICKUP_DEPTH:f32 = 2.0;
+static PICKUP_WIDTH:f32 = 1.0;
+static POINTS:u32 = 50;
+
+pub struct Pickup {
+    pub points: u32,
+}
+
+pub struct PickupDropEvent {
+    pub translation: Vec3,
+}
+
+pub struct PickupPlugin;
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<PickupDropEvent>()
+           .add_system_set_to_stage(replay::FIXED_UPDATE,
+              replay::on_tick()
+                  .with_system(update_pickups.system().label("movement").after("player_input"))
+                  .with_system(collect_pickups.system().label("collisions").after("movement"))
+                  .with_system(handle_drop_event.system().after("collisions"))
+          );
+    }
+}
+
+fn handle_drop_event(
+    mut commands: Commands,
+    game_meshes: Res<field::GameMeshes>,
+    mut drop_event_reader: EventReader<PickupDropEvent>,
+) {
+    for event in drop_event_reader.iter() {
+        commands.spawn_bundle(PbrBundle {
+                    mesh: game_meshes.pickup.clone(),
+                    material: game_meshes.pickup_material.clone(),
+                    transform: Transform::from_translation(event.translation),
+                    ..Default::default()
+                })
+                .insert(Pickup { points: POINTS })
+                .insert(field::LevelEntity);
+    }
+}
+
+fn update_pickups(
+    mut commands: Commands,
+    mut pickups: Query<(Entity, &mut Transform), With<Pickup>>,
+) {
+    for (entity, mut transform) in pickups.iter_mut() {
+        transform.translation.x -= replay::TICK_SECONDS * SPEED;
+
+        if transform.translation.x < DESPAWN_POINT {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    players: Query<(&player::Player, &Transform)>,
+    pickups: Query<(Entity, &Pickup, &Transform)>,
+    mut score_event_writer: EventWriter<score::ScoreEvent>,
+) {
+    for (pickup_entity, pickup, pickup_transform) in pickups.iter() {
+        for (player, player_transform) in players.iter() {
+            if enemy::lane_distance(pickup_transform.translation, player_transform.translation) > PICKUP_WIDTH
+            || (pickup_transform.translation.x - player_transform.translation.x).abs() > PICKUP_DEPTH {
+                continue;
+            }
+
+            commands.entity(pickup_entity).despawn_recursive();
+            score_event_writer.send(score::ScoreEvent {
+                player: player.id,
+                kind: score::ScoreKind::Pickup(pickup.points),
+            });
+            break;
+        }
+    }
+}
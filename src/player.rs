@@ -1,33 +1,255 @@
 use bevy::prelude::*;
+use bevy::ecs::query::{FilterFetch, WorldQuery};
 use std::collections::HashMap;
 use lazy_static::lazy_static;
+use serde::{Serialize, Deserialize};
 
-use crate::{Position, Direction, game_controller, bullet};
+use crate::{Depth, Position, Direction, bullet, enemy, input_map, replay, score};
 
 static SPACE:f32 = 3.0;
 static CENTER:f32 = 5.0;
+// between depth rows along x. Which row something is in is tracked separately, see enemy::touching
+static DEPTH_SPACE:f32 = 5.0;
+pub static STARTING_BOMBS:u32 = 3;
+// the keyboard and gamepad are this player's
+pub static LOCAL_PLAYER:usize = 0;
+// how far ahead of the ship a bomb destroys enemies
+static BOMB_RANGE:f32 = 40.0;
+// the time between shots for every device and fire mode
+static REPEAT_SECONDS:f32 = 0.1;
+static MOVE_SECONDS:f32 = 0.1;
+static MOVE_ACTIONS: [input_map::Action; 4] = [
+    input_map::Action::MoveUp, input_map::Action::MoveDown,
+    input_map::Action::MoveLeft, input_map::Action::MoveRight,
+];
 
 lazy_static!{
+    // lane positions as (z, y), matching how Moveable maps translations
     static ref POSITION_MAP: HashMap<Position, Vec2> = [
-        (Position::TopCenter, Vec2::new(CENTER, SPACE)),
-        (Position::BottomCenter ,Vec2::new(CENTER, -SPACE)),
-        (Position::Center, Vec2::new(CENTER, CENTER)),
+        (Position::TopCenter, Vec2::new(0.0, CENTER + SPACE)),
+        (Position::BottomCenter ,Vec2::new(0.0, CENTER - SPACE)),
+        (Position::Center, Vec2::new(0.0, CENTER)),
 
-        (Position::TopLeft, Vec2::new(-SPACE, SPACE)),
-        (Position::BottomLeft, Vec2::new(-SPACE, -SPACE)),
+        (Position::TopLeft, Vec2::new(-SPACE, CENTER + SPACE)),
+        (Position::BottomLeft, Vec2::new(-SPACE, CENTER - SPACE)),
         (Position::Left, Vec2::new(-SPACE, CENTER)),
 
-        (Position::TopRight, Vec2::new(CENTER, CENTER)),
-        (Position::BottomRight, Vec2::new(SPACE, -SPACE)),
+        (Position::TopRight, Vec2::new(SPACE, CENTER + SPACE)),
+        (Position::BottomRight, Vec2::new(SPACE, CENTER - SPACE)),
         (Position::Right, Vec2::new(SPACE, CENTER)),
     ].iter().copied().collect();
 }
 
-pub struct Player;
+pub fn lane_translation(position: &Position) -> Vec2 {
+    POSITION_MAP[position]
+}
+
+// the lane a (z, y) point is in, if it's in one
+pub fn lane_at(point: Vec2) -> Option<Position> {
+    POSITION_MAP.iter()
+                .find(|(_, lane)| lane.distance(point) < SPACE / 2.0)
+                .map(|(position, _)| *position)
+}
+
+fn direction_offset(direction: Direction) -> Vec2 {
+    match direction {
+        Direction::Up => Vec2::new(0.0, SPACE),
+        Direction::Down => Vec2::new(0.0, -SPACE),
+        Direction::Left => Vec2::new(-SPACE, 0.0),
+        Direction::Right => Vec2::new(SPACE, 0.0),
+        Direction::UpLeft => Vec2::new(-SPACE, SPACE),
+        Direction::UpRight => Vec2::new(SPACE, SPACE),
+        Direction::DownLeft => Vec2::new(-SPACE, -SPACE),
+        Direction::DownRight => Vec2::new(SPACE, -SPACE),
+        // depth moves stay in the same lane
+        Direction::Forward | Direction::Back => Vec2::ZERO,
+    }
+}
+
+pub fn depth_translation(depth: &Depth) -> f32 {
+    match depth {
+        Depth::Back => -DEPTH_SPACE,
+        Depth::Middle => 0.0,
+        Depth::Front => DEPTH_SPACE,
+    }
+}
+
+// the row one move away, the same row if that move would leave the grid
+pub fn neighbor_depth(depth: &Depth, direction: Direction) -> Depth {
+    match (direction, depth) {
+        (Direction::Forward, Depth::Back) => Depth::Middle,
+        (Direction::Forward, _) => Depth::Front,
+        (Direction::Back, Depth::Front) => Depth::Middle,
+        (Direction::Back, _) => Depth::Back,
+        _ => *depth,
+    }
+}
+
+// the lane one move away, None if that move would leave the grid
+pub fn neighbor_lane(position: &Position, direction: Direction) -> Option<Position> {
+    lane_at(lane_translation(position) + direction_offset(direction))
+}
+
+pub struct Player {
+    pub id: usize,
+}
+
+pub struct PlayerStatus {
+    pub lives: u32,
+    pub weapon_level: u32,
+    pub bombs: u32,
+}
+
+impl PlayerStatus {
+    pub fn new(lives: u32) -> Self {
+        PlayerStatus {
+            lives,
+            weapon_level: 1,
+            bombs: STARTING_BOMBS,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FireMode {
+    // fires while fire is held
+    Hold,
+    // a press starts firing and the next one stops it
+    Toggle,
+    // always firing
+    Auto,
+}
+
+impl Default for FireMode {
+    fn default() -> Self {
+        FireMode::Hold
+    }
+}
+
+impl FireMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FireMode::Hold => "HOLD",
+            FireMode::Toggle => "TOGGLE",
+            FireMode::Auto => "AUTO",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            FireMode::Hold => FireMode::Toggle,
+            FireMode::Toggle => FireMode::Auto,
+            FireMode::Auto => FireMode::Hold,
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        match self {
+            FireMode::Hold => FireMode::Auto,
+            FireMode::Toggle => FireMode::Hold,
+            FireMode::Auto => FireMode::Toggle,
+        }
+    }
+}
+
+pub struct Firing {
+    mode: FireMode,
+    toggled_on: bool,
+    cooldown: f32,
+}
+
+impl Firing {
+    // whether this player wants to be shooting on this tick
+    fn wants_to_fire(&mut self, tick_input: &replay::TickInput) -> bool {
+        match self.mode {
+            FireMode::Hold => tick_input.pressed(input_map::Action::Fire),
+            FireMode::Toggle => {
+                if tick_input.just_pressed(input_map::Action::Fire) {
+                    self.toggled_on = !self.toggled_on;
+                }
+                self.toggled_on
+            },
+            FireMode::Auto => true,
+        }
+    }
+}
+
+// holds a fresh up, down, left or right back for a moment in case
+// the other half of a diagonal is pressed just after it
+pub struct DiagonalWindow {
+    seconds: f32,
+    // the direction that's waiting and how long it's waited
+    waiting: Option<(Direction, f32)>,
+}
+
+impl DiagonalWindow {
+    pub fn new(window_ms: u32) -> Self {
+        DiagonalWindow {
+            seconds: window_ms as f32 / 1000.0,
+            waiting: None,
+        }
+    }
+
+    // the move to make this tick, if any
+    fn resolve(&mut self, wanted: Option<Direction>, fresh_press: bool) -> Option<Direction> {
+        match (wanted, self.waiting.take()) {
+            (Some(direction), _) if direction.is_diagonal() => Some(direction),
+            // let go before the window closed, it was still a tap
+            (None, Some((direction, _))) => Some(direction),
+            (Some(direction), Some((_, waited))) if waited + replay::TICK_SECONDS < self.seconds => {
+                self.waiting = Some((direction, waited + replay::TICK_SECONDS));
+                None
+            },
+            (Some(direction), None) if fresh_press && self.seconds > 0.0 => {
+                self.waiting = Some((direction, 0.0));
+                None
+            },
+            (wanted, _) => wanted,
+        }
+    }
+}
+
+// players with this can also move between the depth rows
+pub struct DepthLanes;
+
+pub struct PlayerHitEvent {
+    pub player: usize,
+}
 
 pub struct Moveable {
     position: Position, 
+    depth: Depth,
     movement: Movement,
+    // how far into a move it starts, from the input offset setting
+    head_start: f32,
+}
+
+impl Moveable {
+    pub fn new(input_offset_ms: u32) -> Self {
+        Moveable {
+            position: Position::Center,
+            depth: Depth::Middle,
+            movement: Movement::Stopped,
+            // at least half the move is always shown
+            head_start: (input_offset_ms as f32 / 1000.0).min(MOVE_SECONDS / 2.0),
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.movement == Movement::Stopped
+    }
+
+    pub fn queue(&mut self, direction: Direction) {
+        self.movement = Movement::Queued(direction);
+    }
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -50,39 +272,56 @@ pub struct MovementInfo {
 
     start_position: Position,
     end_position: Position,
+
+    // along x, lane moves keep it where it is
+    start_x: f32,
+    end_x: f32,
+
+    start_depth: Depth,
+    end_depth: Depth,
 }
 
 pub fn spawn_player(
     commands: &mut Commands, 
-    meshes: &mut ResMut<Assets<Mesh>>,
+    game_meshes: &crate::field::GameMeshes,
+    id: usize,
+    lives: u32,
+    fire_mode: FireMode,
+    input_offset_ms: u32,
+    diagonal_window_ms: u32,
 ) -> Entity {
     commands.spawn_bundle(PbrBundle {
                 transform: Transform::from_translation(Vec3::new(0.0, CENTER, 0.0)),
                 ..Default::default()
             })
-            .insert(Player { })
-            .insert(Moveable {
-                position: Position::Center,
-                movement: Movement::Stopped,
+            .insert(Player { id })
+            .insert(crate::field::LevelEntity)
+            .insert(PlayerStatus::new(lives))
+            .insert(Firing {
+                mode: fire_mode,
+                toggled_on: false,
+                cooldown: 0.0,
             })
+            .insert(Moveable::new(input_offset_ms))
+            .insert(DiagonalWindow::new(diagonal_window_ms))
             .with_children(|parent|  {
                 parent.spawn_bundle(PbrBundle {
-                    mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+                    mesh: game_meshes.player.clone(),
                     ..Default::default()
                 });
             })
             .id()
 }
 
-pub fn update_moveables(
-    mut moveable: Query<(&mut Moveable, &mut Transform)>,
-    time: Res<Time>,
-) {
+// generic so the latency screen can move its test ship with the same code as the player
+pub fn update_moveables<F: WorldQuery>(
+    mut moveable: Query<(&mut Moveable, &mut Transform), F>,
+) where F::Fetch: FilterFetch {
     for (mut moveable, mut transform) in moveable.iter_mut() {
         moveable.movement =
         match moveable.movement {
             Movement::Moving(mut movement) => {
-                movement.current_movement_time += time.delta_seconds();
+                movement.current_movement_time += replay::TICK_SECONDS;
 
                 let new_translation = movement.start_translation.lerp(movement.end_translation, 
                                                                   movement.current_movement_time / movement.end_movement_time);
@@ -91,8 +330,10 @@ pub fn update_moveables(
                 if movement.current_movement_time >= movement.end_movement_time {
                     transform.translation.y = movement.end_translation.y;
                     transform.translation.z = movement.end_translation.x;
+                    transform.translation.x = movement.end_x;
                     transform.rotation = movement.end_rotation;
                     moveable.position = movement.end_position;
+                    moveable.depth = movement.end_depth;
 
                     Movement::Stopped
                 } else {
@@ -108,6 +349,9 @@ pub fn update_moveables(
                         }
                     }
 
+                    let progress = (movement.current_movement_time / movement.end_movement_time).min(1.0);
+                    transform.translation.x = movement.start_x + (movement.end_x - movement.start_x) * progress;
+
                     // update position if we're close enough
                     if moveable.position != movement.end_position && 
                         transform.translation.as_i32().y == movement.end_translation.as_i32().y &&
@@ -129,8 +373,8 @@ pub fn update_moveables(
             Movement::Queued(direction) => {
                 let mut new_movement = 
                     MovementInfo {
-                        current_movement_time: 0.0,
-                        end_movement_time: 0.10,
+                        current_movement_time: moveable.head_start,
+                        end_movement_time: MOVE_SECONDS,
 
                         start_translation: Vec2::new(transform.translation.z, transform.translation.y),
                         end_translation: Vec2::new(transform.translation.z, transform.translation.y),
@@ -140,6 +384,12 @@ pub fn update_moveables(
 
                         start_position: moveable.position,
                         end_position: moveable.position,
+
+                        start_x: transform.translation.x,
+                        end_x: transform.translation.x,
+
+                        start_depth: moveable.depth,
+                        end_depth: moveable.depth,
                     };
 
                 match direction {
@@ -194,12 +444,25 @@ pub fn update_moveables(
                                 Position::TopLeft => Position::TopCenter,
                                 _ => new_movement.start_position
                             };
-                    }
+                    },
+                    // straight to the corner cell in one move
+                    Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight => {
+                        if let Some(end_position) = neighbor_lane(&moveable.position, direction) {
+                            new_movement.end_translation += direction_offset(direction);
+                            new_movement.end_position = end_position;
+                        }
+                    },
+                    Direction::Forward | Direction::Back => {
+                        new_movement.end_depth = neighbor_depth(&moveable.depth, direction);
+                        new_movement.end_x = depth_translation(&new_movement.end_depth);
+                    },
                 }
 
-                if new_movement.end_position != new_movement.start_position {
+                if new_movement.end_position != new_movement.start_position
+                || new_movement.end_depth != new_movement.start_depth {
                     Movement::Moving(new_movement)
                 } else {
+                    // off the edge of the grid
                     Movement::Stopped
                 }
             },
@@ -209,64 +472,130 @@ pub fn update_moveables(
 }
 
 pub fn player_input(
-    keyboard_input: Res<Input<KeyCode>>,
-    time: Res<Time>, 
-    mut player: Query<(&mut Moveable, &Transform), With<Player>>,
-    mut action_buffer: Local<Option::<u128>>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<Input<GamepadButton>>,
-    gamepad: Option<Res<game_controller::GameController>>,
+    mut player: Query<(&Player, &mut Moveable, &mut Firing, &mut DiagonalWindow, &Transform)>,
+    tick_input: Res<replay::TickInput>,
     mut bullet_event_writer: EventWriter<bullet::BulletEvent>,
 ) {
-    let time_buffer = 100;
+    let pressed = |action| tick_input.pressed(action);
 
-    let time_since_startup = time.time_since_startup().as_millis();
-    if let Some(time_since_action) = *action_buffer {
-        if time_since_startup - time_since_action > time_buffer {
-            *action_buffer = None;
-        }
-    }
-
-    let pressed_buttons = game_controller::get_pressed_buttons(&axes, &buttons, gamepad);
-    for (mut player, transform) in player.iter_mut() {
-        if (keyboard_input.pressed(KeyCode::Space) 
-        || keyboard_input.pressed(KeyCode::Return) 
-        || keyboard_input.pressed(KeyCode::J) 
-        || pressed_buttons.contains(&game_controller::GameButton::Action))
-        && action_buffer.is_none() {
-            *action_buffer = Some(time.time_since_startup().as_millis());
+    for (player_id, mut player, mut firing, mut diagonal_window, transform) in player.iter_mut() {
+        firing.cooldown = (firing.cooldown - replay::TICK_SECONDS).max(0.0);
+        if firing.wants_to_fire(&tick_input) && firing.cooldown <= 0.0 {
+            firing.cooldown = REPEAT_SECONDS;
             bullet_event_writer.send(bullet::BulletEvent {
                 start: transform.translation,
-                direction: Vec3::new(1.0, 0.0, 0.0)
+                direction: tick_input.aim_direction(),
+                owner: bullet::Owner::Player(player_id.id),
+                depth: Some(player.depth()),
             });
         }
 
-        let mut move_dir = None;
-        if keyboard_input.pressed(KeyCode::W) 
-         || keyboard_input.pressed(KeyCode::Up) 
-         || pressed_buttons.contains(&game_controller::GameButton::Up) {
-            move_dir = Some(Direction::Up); 
+        let mut vertical = None;
+        if pressed(input_map::Action::MoveUp) {
+            vertical = Some(Direction::Up); 
         }
-        if keyboard_input.pressed(KeyCode::S) 
-           || keyboard_input.pressed(KeyCode::Down) 
-           || pressed_buttons.contains(&game_controller::GameButton::Down) {
-            move_dir = Some(Direction::Down); 
+        if pressed(input_map::Action::MoveDown) {
+            vertical = Some(Direction::Down); 
         }
-        if keyboard_input.pressed(KeyCode::A) 
-           || keyboard_input.pressed(KeyCode::Left) 
-           || pressed_buttons.contains(&game_controller::GameButton::Left) {
-            move_dir = Some(Direction::Left); 
+        let mut horizontal = None;
+        if pressed(input_map::Action::MoveLeft) {
+            horizontal = Some(Direction::Left); 
         }
-        if keyboard_input.pressed(KeyCode::D) 
-           || keyboard_input.pressed(KeyCode::Right) 
-           || pressed_buttons.contains(&game_controller::GameButton::Right) {
-            move_dir = Some(Direction::Right); 
+        if pressed(input_map::Action::MoveRight) {
+            horizontal = Some(Direction::Right); 
         }
 
+        // a diagonal off the edge of the grid slides along it instead
+        let position = player.position;
+        let move_dir = match (vertical, horizontal) {
+            (Some(vertical), Some(horizontal)) => [Direction::diagonal(vertical, horizontal), horizontal, vertical]
+                                                      .iter()
+                                                      .copied()
+                                                      .find(|direction| neighbor_lane(&position, *direction).is_some()),
+            (vertical, horizontal) => horizontal.or(vertical),
+        };
+
         if player.movement == Movement::Stopped {
-            if let Some(move_dir) = move_dir {
+            let fresh_press = MOVE_ACTIONS.iter().any(|action| tick_input.just_pressed(*action));
+            if let Some(move_dir) = diagonal_window.resolve(move_dir, fresh_press) {
                 player.movement = Movement::Queued(move_dir);
             }
         }
     }
 }
+
+// runs after player_input, so changing lanes comes first when both are held
+pub fn depth_input(
+    mut players: Query<(&mut Moveable, &DiagonalWindow), With<DepthLanes>>,
+    tick_input: Res<replay::TickInput>,
+) {
+    let mut depth_dir = None;
+    if tick_input.pressed(input_map::Action::MoveForward) {
+        depth_dir = Some(Direction::Forward);
+    }
+    if tick_input.pressed(input_map::Action::MoveBack) {
+        depth_dir = Some(Direction::Back);
+    }
+
+    for (mut player, diagonal_window) in players.iter_mut() {
+        if let Some(depth_dir) = depth_dir {
+            // a lane move waiting on its diagonal window still goes first
+            if player.is_stopped() && diagonal_window.waiting.is_none() {
+                player.queue(depth_dir);
+            }
+        }
+    }
+}
+
+// a bomb clears every enemy shot on screen and the enemies just ahead, bosses have to be shot down
+pub fn use_bomb(
+    mut commands: Commands,
+    tick_input: Res<replay::TickInput>,
+    mut players: Query<(&Player, &mut PlayerStatus, &Transform)>,
+    bullets: Query<(Entity, &bullet::Bullet)>,
+    mut enemies: Query<(Entity, &mut enemy::Enemy, &Transform), Without<enemy::Boss>>,
+    mut score_event_writer: EventWriter<score::ScoreEvent>,
+) {
+    if !tick_input.just_pressed(input_map::Action::Bomb) {
+        return;
+    }
+
+    for (player, mut status, transform) in players.iter_mut() {
+        if player.id != LOCAL_PLAYER || status.bombs == 0 {
+            continue;
+        }
+
+        status.bombs -= 1;
+        for (entity, bullet) in bullets.iter() {
+            if let bullet::Owner::Enemy = bullet.owner {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        for (entity, mut enemy, enemy_transform) in enemies.iter_mut() {
+            let distance = enemy_transform.translation.x - transform.translation.x;
+            if enemy.hp > 0 && (0.0..=BOMB_RANGE).contains(&distance) {
+                // so a shot landing on the same tick doesn't score it again
+                enemy.hp = 0;
+                commands.entity(entity).despawn_recursive();
+                score_event_writer.send(score::ScoreEvent {
+                    player: player.id,
+                    kind: score::ScoreKind::Kill(enemy.points),
+                });
+            }
+        }
+    }
+}
+
+pub fn handle_player_hits(
+    mut player_hit_event_reader: EventReader<PlayerHitEvent>,
+    mut players: Query<(&Player, &mut PlayerStatus)>,
+) {
+    for event in player_hit_event_reader.iter() {
+        for (player, mut status) in players.iter_mut() {
+            if player.id == event.player && status.lives > 0 {
+                status.lives -= 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+
+use crate::{glyphs, hud, settings, ui,
+            game_controller::{GameButton, GameController, GamepadInfo, MenuInput},
+            input_map::{self, Action, GamepadProfile, InputMap}};
+
+static STICKS: [(&str, GamepadAxisType, GamepadAxisType); 2] = [
+    ("LEFT STICK", GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+    ("RIGHT STICK", GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+];
+
+// the gamepad whose profile is in the InputMap, no identity means the default profile
+#[derive(Default)]
+pub struct ActiveProfile {
+    identity: Option<String>,
+    name: String,
+    // what was applied, so only profiles that were actually changed get saved
+    applied: GamepadProfile,
+}
+
+pub struct ProfilesPlugin;
+impl Plugin for ProfilesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ActiveProfile>()
+           .add_system(apply_gamepad_profile.system())
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::GamepadSetup)
+                   .with_system(setup_gamepad_setup.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::GamepadSetup)
+                   .with_system(identify_gamepad.system())
+                   .with_system(update_gamepad_setup.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::GamepadSetup)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+// keeps the InputMap's gamepad half in settings, under the active gamepad or as the default
+pub fn store_bindings(
+    settings: &mut settings::Settings,
+    active_profile: &mut ActiveProfile,
+    input_map: &InputMap,
+) {
+    let profile = input_map.gamepad_profile(&active_profile.name);
+    let changed = profile.bindings != active_profile.applied.bindings
+                  || profile.stick_settings != active_profile.applied.stick_settings;
+
+    let mut saved = input_map.clone();
+    if let Some(identity) = active_profile.identity.as_ref() {
+        if changed {
+            settings.gamepad_profiles.insert(identity.clone(), profile.clone());
+        }
+        // only the keyboard bindings go in the default profile then
+        saved.apply_gamepad_profile(&settings.input_map.gamepad_profile(""));
+    }
+    settings.input_map = saved;
+    active_profile.applied = profile;
+}
+
+fn apply_gamepad_profile(
+    mut active_profile: ResMut<ActiveProfile>,
+    mut input_map: ResMut<InputMap>,
+    mut settings: ResMut<settings::Settings>,
+    gamepad_info: Res<GamepadInfo>,
+    gamepad: Option<Res<GameController>>,
+) {
+    let gamepad = gamepad.map(|gamepad| gamepad.0);
+    let identity = gamepad.and_then(|gamepad| gamepad_info.identities.get(&gamepad)).cloned();
+    if identity == active_profile.identity {
+        return;
+    }
+
+    // the controls screen saves its changes, but the gamepad can be switched underneath it
+    store_bindings(&mut settings, &mut active_profile, &input_map);
+
+    let name = gamepad.and_then(|gamepad| gamepad_info.names.get(&gamepad)).cloned().unwrap_or_default();
+    let profile = match identity.as_ref().and_then(|identity| settings.gamepad_profiles.get(identity)) {
+        Some(profile) => {
+            println!("Using the saved profile for {}", name);
+            profile.clone()
+        },
+        None => settings.input_map.gamepad_profile(&name),
+    };
+
+    input_map.apply_gamepad_profile(&profile);
+    *active_profile = ActiveProfile {
+        identity,
+        name,
+        applied: profile,
+    };
+}
+
+fn setup_gamepad_setup(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 32.0);
+}
+
+// the first button pressed on any other gamepad makes it the one that plays
+fn identify_gamepad(
+    mut commands: Commands,
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepad: Option<Res<GameController>>,
+) {
+    if menu_input.pressed(GameButton::Start) {
+        menu_input.consume();
+        state.pop().unwrap();
+        return;
+    }
+
+    let current = gamepad.map(|gamepad| gamepad.0);
+    if let Some(GamepadButton(pressed, _)) = buttons.get_just_pressed().find(|button| Some(button.0) != current) {
+        println!("Switching to gamepad {:?}", pressed);
+        commands.insert_resource(GameController(*pressed));
+        menu_input.consume();
+    }
+}
+
+// shows the raw stick next to what the deadzones make of it, to see how much a stick drifts
+fn update_gamepad_setup(
+    input_map: Res<InputMap>,
+    settings: Res<settings::Settings>,
+    active_profile: Res<ActiveProfile>,
+    gamepad_info: Res<GamepadInfo>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad: Option<Res<GameController>>,
+    glyphs: Res<glyphs::Glyphs>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    let mut value = "GAMEPAD SETUP\n\nPRESS ANY BUTTON ON A GAMEPAD TO USE IT\n\n".to_string();
+
+    match gamepad.map(|gamepad| gamepad.0) {
+        Some(gamepad) => {
+            let name = gamepad_info.names.get(&gamepad).map_or("UNKNOWN GAMEPAD", |name| name.as_str());
+            let saved = match active_profile.identity.as_ref() {
+                Some(identity) => settings.gamepad_profiles.contains_key(identity),
+                None => false,
+            };
+            value.push_str(&format!("{}\nPROFILE: {}\n\n", name.to_uppercase(), if saved { "SAVED" } else { "DEFAULT" }));
+
+            let processed = input_map::axis_values(&axes, gamepad, &input_map.stick_settings);
+            for (label, x_axis, y_axis) in STICKS.iter() {
+                let raw = |axis_type| axes.get(GamepadAxis(gamepad, axis_type)).unwrap_or(0.0);
+                value.push_str(&format!("{}  {:>5.2} {:>5.2}  ->  {:>5.2} {:>5.2}\n",
+                                        label, raw(*x_axis), raw(*y_axis), processed[x_axis], processed[y_axis]));
+            }
+        },
+        None => value.push_str("NO GAMEPAD\n"),
+    }
+
+    value.push_str(&format!("\n{} BACK", glyphs.prompt(Action::Pause)));
+    ui::set_screen_text(&mut texts, &value);
+}
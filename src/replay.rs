@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use bevy::core::FixedTimestep;
+use bevy::ecs::schedule::ShouldRun;
+use rand::{SeedableRng, rngs::StdRng};
+use serde::{Serialize, Deserialize};
+
+use crate::{aim, attract, campaign, difficulty, player, save, settings,
+            game_controller::PilotInput, input_map::{self, Action, InputMap}};
+
+// gameplay runs in its own stage at a fixed rate instead of once a frame, so a run
+// plays out the same way from the same inputs no matter how fast it's drawn
+pub static FIXED_UPDATE:&str = "fixed_update";
+pub static TICK_SECONDS:f32 = 1.0 / 60.0;
+
+static REPLAY_FILE:&str = "replay.ron";
+// bumped whenever the simulation changes, an older replay wouldn't play back the same
+static REPLAY_VERSION:u32 = 4;
+// aim directions are rounded to this so a replay fires exactly the shots the run did
+static AIM_PRECISION:f32 = 1000.0;
+
+// everything needed to play a stage back, the inputs are kept as runs of
+// identical ticks since they hardly change from one tick to the next
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub stage: String,
+    pub difficulty: difficulty::DifficultyLevel,
+    pub multipliers: difficulty::Multipliers,
+    #[serde(default)]
+    pub fire_mode: player::FireMode,
+    #[serde(default)]
+    pub input_offset_ms: u32,
+    pub diagonal_window_ms: u32,
+    #[serde(default)]
+    pub depth_lanes: bool,
+    pub ticks: Vec<(ReplayTick, u32)>,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayTick {
+    pressed: u32,
+    aim: Option<(i16, i16, i16)>,
+}
+
+impl Replay {
+    pub fn load() -> Option<Self> {
+        save::load(REPLAY_FILE, REPLAY_VERSION)
+    }
+
+    fn save(&self) {
+        save::save(REPLAY_FILE, REPLAY_VERSION, self);
+    }
+
+    fn push(&mut self, tick: ReplayTick) {
+        match self.ticks.last_mut() {
+            Some((last, count)) if *last == tick => *count += 1,
+            _ => self.ticks.push((tick, 1)),
+        }
+    }
+}
+
+// the actions the gameplay systems see for one tick, whether they came from
+// the player, the attract mode pilot or a replay
+#[derive(Default)]
+pub struct TickInput {
+    pressed: u32,
+    just_pressed: u32,
+    aim: Option<Vec3>,
+    // presses since the last tick, so a tap between two ticks isn't missed
+    latched: u32,
+}
+
+impl TickInput {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed & action_bit(action) != 0
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed & action_bit(action) != 0
+    }
+
+    // which way shots go, straight ahead unless the player is aiming
+    pub fn aim_direction(&self) -> Vec3 {
+        self.aim.unwrap_or_else(|| Vec3::new(1.0, 0.0, 0.0))
+    }
+}
+
+pub struct GameRng(pub StdRng);
+
+#[derive(Default)]
+struct Recording {
+    replay: Option<Replay>,
+}
+
+// set from the title screen before InGame starts, the run then counts as a demo
+#[derive(Default)]
+pub struct Playback {
+    pub replay: Option<Replay>,
+    pub finished: bool,
+    // which run of ticks is playing and how far into it
+    run: usize,
+    run_tick: u32,
+    previous_difficulty: Option<(difficulty::DifficultyLevel, difficulty::Multipliers)>,
+}
+
+impl Playback {
+    pub fn active(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // the input for the next tick, None once the recording runs out
+    fn next_tick(&mut self) -> Option<ReplayTick> {
+        let (pressed, count) = *self.replay.as_ref()?.ticks.get(self.run)?;
+        self.run_tick += 1;
+        if self.run_tick >= count {
+            self.run += 1;
+            self.run_tick = 0;
+        }
+
+        Some(pressed)
+    }
+}
+
+fn action_bit(action: Action) -> u32 {
+    let index = input_map::ACTIONS.iter().position(|other| *other == action).unwrap_or(0);
+    1 << index
+}
+
+fn action_bits<'a>(actions: impl Iterator<Item = &'a Action>) -> u32 {
+    actions.fold(0, |bits, action| bits | action_bit(*action))
+}
+
+fn quantize_aim(direction: Vec3) -> (i16, i16, i16) {
+    let direction = (direction * AIM_PRECISION).round();
+    (direction.x as i16, direction.y as i16, direction.z as i16)
+}
+
+fn dequantize_aim((x, y, z): (i16, i16, i16)) -> Vec3 {
+    Vec3::new(x as f32, y as f32, z as f32) / AIM_PRECISION
+}
+
+// the fixed stage has no state driver of its own, so its systems check the state with this
+fn in_game(state: Res<State<crate::AppState>>) -> ShouldRun {
+    if *state.current() == crate::AppState::InGame {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+pub fn on_tick() -> SystemSet {
+    SystemSet::new().with_run_criteria(in_game.system())
+}
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // single threaded so the systems run in the same order on every tick
+        app.add_stage_after(CoreStage::Update, FIXED_UPDATE,
+                            SystemStage::single_threaded().with_run_criteria(FixedTimestep::step(TICK_SECONDS as f64)))
+           .init_resource::<TickInput>()
+           .init_resource::<Recording>()
+           .init_resource::<Playback>()
+           .insert_resource(GameRng(StdRng::seed_from_u64(0)))
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(start_run.system().before("loading_level"))
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(latch_presses.system())
+           )
+           .add_system_set_to_stage(FIXED_UPDATE,
+               on_tick()
+                   .with_system(sample_input.system().label("tick_input"))
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(save_recording.system())
+           )
+           // playback stops at the first game over, so the replay ends where the run first did
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::Continue)
+                   .with_system(save_recording.system())
+           )
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::MainMenu)
+                   .with_system(stop_playback.system())
+           );
+    }
+}
+
+fn start_run(
+    mut rng: ResMut<GameRng>,
+    mut tick_input: ResMut<TickInput>,
+    mut recording: ResMut<Recording>,
+    mut playback: ResMut<Playback>,
+    mut difficulty: ResMut<difficulty::Difficulty>,
+    attract: Res<attract::AttractMode>,
+    campaign: Res<campaign::Campaign>,
+    settings: Res<settings::Settings>,
+) {
+    *tick_input = TickInput::default();
+    recording.replay = None;
+    playback.run = 0;
+    playback.run_tick = 0;
+    playback.finished = false;
+
+    if let Some((seed, level, multipliers)) = playback.replay.as_ref().map(|replay| (replay.seed, replay.difficulty, replay.multipliers)) {
+        // put back when the title screen comes up again
+        if playback.previous_difficulty.is_none() {
+            playback.previous_difficulty = Some((difficulty.level, difficulty.multipliers));
+        }
+        difficulty.level = level;
+        difficulty.multipliers = multipliers;
+        rng.0 = StdRng::seed_from_u64(seed);
+        return;
+    }
+
+    let seed = rand::random();
+    rng.0 = StdRng::seed_from_u64(seed);
+
+    if !attract.active {
+        recording.replay = Some(Replay {
+            seed,
+            stage: campaign.current_stage().id.to_string(),
+            difficulty: difficulty.level,
+            multipliers: difficulty.multipliers,
+            // the recorded input is the local player's, so is the fire mode
+            fire_mode: settings.fire_mode(player::LOCAL_PLAYER),
+            input_offset_ms: settings.input_offset_ms,
+            diagonal_window_ms: settings.diagonal_window_ms,
+            depth_lanes: settings.depth_lanes,
+            ticks: vec!(),
+        });
+    }
+}
+
+fn latch_presses(
+    input_map: Res<InputMap>,
+    mut tick_input: ResMut<TickInput>,
+) {
+    tick_input.latched |= action_bits(input_map::ACTIONS.iter().filter(|action| input_map.just_pressed(**action)));
+}
+
+fn sample_input(
+    input_map: Res<InputMap>,
+    aim: Res<aim::Aim>,
+    pilot_input: Res<PilotInput>,
+    mut tick_input: ResMut<TickInput>,
+    mut recording: ResMut<Recording>,
+    mut playback: ResMut<Playback>,
+) {
+    let tick =
+        if playback.active() {
+            let tick = playback.next_tick();
+            playback.finished = tick.is_none();
+            tick.unwrap_or_default()
+        } else if pilot_input.active {
+            ReplayTick {
+                pressed: action_bits(pilot_input.actions.iter()),
+                aim: None,
+            }
+        } else {
+            ReplayTick {
+                pressed: action_bits(input_map::ACTIONS.iter().filter(|action| input_map.pressed(**action))) | tick_input.latched,
+                aim: aim.direction.map(quantize_aim),
+            }
+        };
+
+    tick_input.just_pressed = tick.pressed & !tick_input.pressed;
+    tick_input.pressed = tick.pressed;
+    tick_input.aim = tick.aim.map(dequantize_aim);
+    tick_input.latched = 0;
+
+    if let Some(replay) = recording.replay.as_mut() {
+        replay.push(tick);
+    }
+}
+
+// keeps the last run that was played, restarting or clearing a stage replaces it.
+// Saving also stops the recording, nothing after a continue is kept
+fn save_recording(
+    mut recording: ResMut<Recording>,
+) {
+    if let Some(replay) = recording.replay.take() {
+        if !replay.ticks.is_empty() {
+            replay.save();
+        }
+    }
+}
+
+fn stop_playback(
+    mut playback: ResMut<Playback>,
+    mut difficulty: ResMut<difficulty::Difficulty>,
+) {
+    if let Some((level, multipliers)) = playback.previous_difficulty.take() {
+        difficulty.level = level;
+        difficulty.multipliers = multipliers;
+    }
+
+    *playback = Playback::default();
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+use crate::{glyphs, hud, score, stats, ui, game_controller::{GameButton, MenuInput}, input_map::Action};
+
+pub struct ResultsPlugin;
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+               SystemSet::on_enter(crate::AppState::Results)
+                   .with_system(setup_results.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::Results)
+                   .with_system(update_results.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::Results)
+                   .with_system(ui::cleanup_screen.system())
+           );
+    }
+}
+
+fn setup_results(
+    mut commands: Commands,
+    ui_font: Res<hud::UiFont>,
+) {
+    ui::spawn_screen_text(&mut commands, &ui_font, 40.0);
+}
+
+fn update_results(
+    mut state: ResMut<State<crate::AppState>>,
+    mut menu_input: ResMut<MenuInput>,
+    final_tally: Res<score::FinalTally>,
+    run_stats: Res<stats::RunStats>,
+    glyphs: Res<glyphs::Glyphs>,
+    mut texts: Query<&mut Text, With<ui::ScreenText>>,
+) {
+    let mut value = if run_stats.cleared { "STAGE CLEAR\n\n" } else { "RESULTS\n\n" }.to_string();
+    for (id, player_score) in final_tally.players.iter() {
+        value.push_str(&format!("PLAYER {}\n", id + 1));
+        value.push_str(&format!("SCORE {}\n", player_score.points));
+        value.push_str(&format!("KILLS {}  PICKUPS {}  GRAZES {}  MAX COMBO {}\n\n", 
+                                player_score.kills, player_score.pickups, player_score.grazes, player_score.max_combo));
+    }
+    value.push_str(&format!("SHOTS {}  HITS {}  ACCURACY {:.0}%\n", 
+                            run_stats.shots_fired, run_stats.hits, run_stats.accuracy * 100.0));
+    value.push_str(&format!("DAMAGE {}  DEATHS {}  BOMBS {}\n", 
+                            run_stats.damage_taken, run_stats.deaths, run_stats.bombs_used));
+    value.push_str(&format!("TIME {:.1}s\n\n", run_stats.clear_time));
+    if let Some(grade) = run_stats.grade {
+        value.push_str(&format!("GRADE {}\n\n", grade.name()));
+    }
+    value.push_str(&format!("PRESS {}", glyphs.prompt(Action::Fire)));
+    ui::set_screen_text(&mut texts, &value);
+
+    if menu_input.pressed(GameButton::Action) {
+        menu_input.consume();
+        state.set(crate::AppState::HighScoreEntry).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use std::{fs, path::PathBuf};
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    data: T,
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("pewpewpew"))
+}
+
+// returns None if the file is missing, unreadable or from a different version
+// so callers can fall back to their defaults
+pub fn load<T: DeserializeOwned>(file_name: &str, version: u32) -> Option<T> {
+    let path = data_dir()?.join(file_name);
+    let contents = fs::read_to_string(&path).ok()?;
+
+    match ron::de::from_str::<SaveFile<T>>(&contents) {
+        Ok(save_file) if save_file.version == version => Some(save_file.data),
+        Ok(save_file) => {
+            println!("Ignoring {:?}, expected version {} but found {}", path, version, save_file.version);
+            None
+        },
+        Err(e) => {
+            println!("Ignoring {:?}, could not parse it: {}", path, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(file_name: &str, version: u32, data: &T) {
+    let dir = match data_dir() {
+        Some(dir) => dir,
+        None => {
+            println!("No data directory available, not saving {}", file_name);
+            return;
+        }
+    };
+
+    let contents = match ron::ser::to_string_pretty(&SaveFile { version, data }, ron::ser::PrettyConfig::new()) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Could not serialize {}: {}", file_name, e);
+            return;
+        }
+    };
+
+    // write to a temporary file first so a crash mid-write can't corrupt the save
+    let path = dir.join(file_name);
+    let temp_path = dir.join(format!("{}.tmp", file_name));
+    let result = fs::create_dir_all(&dir)
+                    .and_then(|_| fs::write(&temp_path, contents))
+                    .and_then(|_| fs::rename(&temp_path, &path));
+
+    if let Err(e) = result {
+        println!("Could not save {:?}: {}", path, e);
+    }
+}
+
+// plain JSON for other tools to read, so there's no version wrapper
+pub fn export_json<T: Serialize>(sub_dir: &str, file_name: &str, data: &T) {
+    let dir = match data_dir() {
+        Some(dir) => dir.join(sub_dir),
+        None => {
+            println!("No data directory available, not exporting {}", file_name);
+            return;
+        }
+    };
+
+    let path = dir.join(file_name);
+    let result = serde_json::to_string_pretty(data)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| {
+                        fs::create_dir_all(&dir)
+                            .and_then(|_| fs::write(&path, contents))
+                            .map_err(|e| e.to_string())
+                    });
+
+    if let Err(e) = result {
+        println!("Could not export {:?}: {}", path, e);
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::{difficulty, replay};
+
+static COMBO_WINDOW:f32 = 2.0;
+static KILLS_PER_MULTIPLIER:u32 = 4;
+static MAX_MULTIPLIER:u32 = 16;
+static GRAZE_POINTS:u64 = 10;
+
+pub struct ScoreEvent {
+    pub player: usize,
+    pub kind: ScoreKind,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScoreKind {
+    Kill(u32),
+    Pickup(u32),
+    Graze,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerScore {
+    pub points: u64,
+    pub multiplier: u32,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub kills: u32,
+    pub pickups: u32,
+    pub grazes: u32,
+    combo_timer: f32,
+}
+
+impl Default for PlayerScore {
+    fn default() -> Self {
+        PlayerScore {
+            points: 0,
+            multiplier: 1,
+            combo: 0,
+            max_combo: 0,
+            kills: 0,
+            pickups: 0,
+            grazes: 0,
+            combo_timer: 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Score {
+    pub players: HashMap<usize, PlayerScore>,
+}
+
+#[derive(Default)]
+pub struct FinalTally {
+    pub players: Vec<(usize, PlayerScore)>,
+}
+
+pub struct ScorePlugin;
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ScoreEvent>()
+           .init_resource::<Score>()
+           .init_resource::<FinalTally>()
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(reset_score.system())
+           )
+           .add_system_set_to_stage(replay::FIXED_UPDATE,
+               replay::on_tick()
+                   .with_system(handle_score_events.system().label("score_events").after("collisions"))
+                   .with_system(decay_combos.system().after("score_events"))
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(tally_scores.system())
+           );
+    }
+}
+
+fn reset_score(
+    mut score: ResMut<Score>,
+) {
+    score.players.clear();
+}
+
+fn handle_score_events(
+    mut score: ResMut<Score>,
+    mut score_event_reader: EventReader<ScoreEvent>,
+    difficulty: Res<difficulty::Difficulty>,
+) {
+    for event in score_event_reader.iter() {
+        let player_score = score.players.entry(event.player).or_default();
+        match event.kind {
+            ScoreKind::Kill(points) => {
+                player_score.kills += 1;
+                player_score.combo += 1;
+                player_score.max_combo = player_score.max_combo.max(player_score.combo);
+                player_score.combo_timer = COMBO_WINDOW;
+                player_score.multiplier = player_score.multiplier
+                                                      .max(1 + player_score.combo / KILLS_PER_MULTIPLIER)
+                                                      .min(MAX_MULTIPLIER);
+                player_score.points += difficulty.score(points as u64 * player_score.multiplier as u64);
+            },
+            ScoreKind::Pickup(points) => {
+                player_score.pickups += 1;
+                player_score.points += difficulty.score(points as u64 * player_score.multiplier as u64);
+            },
+            ScoreKind::Graze => {
+                player_score.grazes += 1;
+                player_score.points += difficulty.score(GRAZE_POINTS);
+            }
+        }
+    }
+}
+
+fn decay_combos(
+    mut score: ResMut<Score>,
+) {
+    for player_score in score.players.values_mut() {
+        if player_score.combo_timer <= 0.0 {
+            continue;
+        }
+
+        player_score.combo_timer -= replay::TICK_SECONDS;
+        if player_score.combo_timer <= 0.0 {
+            // the chain is broken, so drop one multiplier step at a time
+            player_score.combo = 0;
+            player_score.multiplier = (player_score.multiplier - 1).max(1);
+            if player_score.multiplier > 1 {
+                player_score.combo_timer = COMBO_WINDOW;
+            }
+        }
+    }
+}
+
+fn tally_scores(
+    score: Res<Score>,
+    mut final_tally: ResMut<FinalTally>,
+) {
+    let mut players: Vec<(usize, PlayerScore)> = score.players.iter()
+                                                     .map(|(id, player_score)| (*id, player_score.clone()))
+                                                     .collect();
+    players.sort_by_key(|(id, _)| *id);
+
+    final_tally.players = players;
+}
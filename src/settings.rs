@@ -0,0 +1,162 @@
+use bevy::window::WindowMode;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::{input_map, player, save};
+
+static SETTINGS_FILE:&str = "settings.ron";
+static SETTINGS_VERSION:u32 = 1;
+
+pub static RESOLUTIONS: [(u32, u32); 4] = [(1280, 720), (1600, 900), (1920, 1080), (2560, 1440)];
+// wgpu only guarantees 1 and 4 samples on every adapter
+pub static MSAA_SAMPLES: [u32; 2] = [1, 4];
+pub static LANGUAGES: [(&str, &str); 1] = [("en", "ENGLISH")];
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    Fullscreen,
+    Borderless,
+    Windowed,
+}
+
+impl WindowModeSetting {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Fullscreen => "FULLSCREEN",
+            WindowModeSetting::Borderless => "BORDERLESS",
+            WindowModeSetting::Windowed => "WINDOWED",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            WindowModeSetting::Fullscreen => WindowModeSetting::Borderless,
+            WindowModeSetting::Borderless => WindowModeSetting::Windowed,
+            WindowModeSetting::Windowed => WindowModeSetting::Fullscreen,
+        }
+    }
+
+    pub fn previous(&self) -> Self {
+        match self {
+            WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+            WindowModeSetting::Borderless => WindowModeSetting::Fullscreen,
+            WindowModeSetting::Windowed => WindowModeSetting::Borderless,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub window_mode: WindowModeSetting,
+    pub resolution: (u32, u32),
+    pub msaa_samples: u32,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub screen_shake: bool,
+    pub language: String,
+    // how long the title screen waits for input before the attract mode starts
+    #[serde(default = "default_attract_idle_seconds")]
+    pub attract_idle_seconds: f32,
+    // fire toward the cursor or right stick instead of straight ahead
+    #[serde(default)]
+    pub aim_mode: bool,
+    #[serde(default)]
+    pub aim_assist: bool,
+    // keyed by player id so each player picks their own, anyone missing fires on hold
+    #[serde(default)]
+    pub fire_modes: HashMap<usize, player::FireMode>,
+    // move tweens start this far along, to make up for the delay the latency screen measures
+    #[serde(default)]
+    pub input_offset_ms: u32,
+    // how long after an up, down, left or right the other half of a diagonal can still be pressed
+    #[serde(default = "default_diagonal_window_ms")]
+    pub diagonal_window_ms: u32,
+    // front, middle and back rows the ship can move between along the x axis
+    #[serde(default)]
+    pub depth_lanes: bool,
+    // only the bindings are kept here, the InputMap resource is what the game reads.
+    // Its gamepad bindings are the profile for gamepads that haven't been set up
+    #[serde(default)]
+    pub input_map: input_map::InputMap,
+    // keyed by the gamepad's identity, see game_controller::GamepadInfo
+    #[serde(default)]
+    pub gamepad_profiles: HashMap<String, input_map::GamepadProfile>,
+}
+
+fn default_attract_idle_seconds() -> f32 {
+    30.0
+}
+
+fn default_diagonal_window_ms() -> u32 {
+    50
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_mode: WindowModeSetting::Borderless,
+            resolution: RESOLUTIONS[0],
+            msaa_samples: 4,
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            screen_shake: true,
+            language: LANGUAGES[0].0.to_string(),
+            attract_idle_seconds: default_attract_idle_seconds(),
+            aim_mode: false,
+            aim_assist: false,
+            fire_modes: HashMap::new(),
+            input_offset_ms: 0,
+            diagonal_window_ms: default_diagonal_window_ms(),
+            depth_lanes: false,
+            input_map: input_map::InputMap::default(),
+            gamepad_profiles: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings: Settings = save::load(SETTINGS_FILE, SETTINGS_VERSION).unwrap_or_default();
+        settings.input_map.add_missing_bindings();
+        settings.input_map.stick_settings.repair();
+        for profile in settings.gamepad_profiles.values_mut() {
+            profile.add_missing_bindings();
+            profile.stick_settings.repair();
+        }
+
+        settings
+    }
+
+    pub fn save(&self) {
+        save::save(SETTINGS_FILE, SETTINGS_VERSION, self);
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.window_mode {
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen { use_size: true },
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+        }
+    }
+
+    // what music and sound effects actually play at, after the master volume
+    pub fn music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    pub fn fire_mode(&self, player: usize) -> player::FireMode {
+        self.fire_modes.get(&player).copied().unwrap_or_default()
+    }
+
+    pub fn language_name(&self) -> &'static str {
+        LANGUAGES.iter()
+                 .find(|(code, _)| *code == self.language)
+                 .map_or(LANGUAGES[0].1, |(_, name)| name)
+    }
+}
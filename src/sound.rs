@@ -0,0 +1,256 @@
+use bevy::{prelude::*,};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source, buffer::SamplesBuffer};
+use serde::Deserialize;
+
+use crate::{asset_loader, bullet, player, score, settings};
+
+static SAMPLE_RATE:u32 = 22050;
+static NOTES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+#[derive(Deserialize)]
+enum Wave {
+    Square,
+    Triangle,
+    Saw,
+    Noise,
+}
+
+// a .tune file, every note or "-" rest lasts one beat and fades out over it
+#[derive(Deserialize)]
+struct TuneFile {
+    tempo: f32,
+    wave: Wave,
+    volume: f32,
+    notes: Vec<String>,
+}
+
+// a tune rendered to mono samples when it's loaded
+#[derive(TypeUuid)]
+#[uuid = "3b9e6f14-7c2d-4a85-b1e0-9d4f2a6c8e57"]
+pub struct Tune {
+    samples: Vec<f32>,
+}
+
+impl Tune {
+    fn source(&self) -> SamplesBuffer<f32> {
+        SamplesBuffer::new(1, SAMPLE_RATE, self.samples.clone())
+    }
+}
+
+// "A4", "C#5" and so on to a frequency, None for a rest
+fn note_frequency(note: &str) -> Result<Option<f32>, anyhow::Error> {
+    if note == "-" {
+        return Ok(None);
+    }
+
+    let split = note.find(|c: char| c.is_ascii_digit() || c == '-')
+                    .ok_or_else(|| anyhow::anyhow!("note {} has no octave", note))?;
+    let (name, octave) = note.split_at(split);
+    let semitone = NOTES.iter()
+                        .position(|n| *n == name)
+                        .ok_or_else(|| anyhow::anyhow!("unknown note {}", note))?;
+    let octave: i32 = octave.parse()?;
+    let midi = (octave + 1) * 12 + semitone as i32;
+
+    Ok(Some(440.0 * 2.0_f32.powf((midi - 69) as f32 / 12.0)))
+}
+
+fn render(tune: &TuneFile) -> Result<Vec<f32>, anyhow::Error> {
+    let beat = (SAMPLE_RATE as f32 * 60.0 / tune.tempo.max(1.0)) as usize;
+    let mut samples = Vec::with_capacity(beat * tune.notes.len());
+    // a fixed noise generator so a tune sounds the same every time
+    let mut noise:u32 = 0x1234_5678;
+
+    for note in tune.notes.iter() {
+        let frequency = note_frequency(note)?;
+        for i in 0..beat {
+            let frequency = match frequency {
+                Some(frequency) => frequency,
+                None => {
+                    samples.push(0.0);
+                    continue;
+                }
+            };
+
+            let phase = (i as f32 * frequency / SAMPLE_RATE as f32).fract();
+            let value = match tune.wave {
+                Wave::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+                Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                Wave::Saw => 2.0 * phase - 1.0,
+                Wave::Noise => {
+                    noise ^= noise << 13;
+                    noise ^= noise >> 17;
+                    noise ^= noise << 5;
+                    noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+                },
+            };
+            let fade = 1.0 - i as f32 / beat as f32;
+            samples.push(value * fade * tune.volume);
+        }
+    }
+
+    Ok(samples)
+}
+
+#[derive(Default)]
+struct TuneLoader;
+impl AssetLoader for TuneLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tune: TuneFile = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(Tune { samples: render(&tune)? }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tune"]
+    }
+}
+
+// rodio is used directly since bevy's Audio can't loop, stop or change the volume of a sound
+pub struct SoundOutput {
+    stream: Option<(OutputStream, OutputStreamHandle)>,
+    music: Option<Sink>,
+}
+
+impl Default for SoundOutput {
+    fn default() -> Self {
+        let stream = match OutputStream::try_default() {
+            Ok(stream) => Some(stream),
+            Err(e) => {
+                println!("No audio output, playing without sound: {}", e);
+                None
+            }
+        };
+
+        SoundOutput {
+            stream,
+            music: None,
+        }
+    }
+}
+
+// the tune to loop as music, it starts once it has loaded
+#[derive(Default)]
+pub struct Music {
+    tune: Option<Handle<Tune>>,
+    changed: bool,
+}
+
+impl Music {
+    pub fn play(&mut self, tune: Handle<Tune>) {
+        self.tune = Some(tune);
+        self.changed = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.tune = None;
+        self.changed = true;
+    }
+}
+
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Tune>()
+           .init_asset_loader::<TuneLoader>()
+           .init_non_send_resource::<SoundOutput>()
+           .init_resource::<Music>()
+           .add_system(update_music.system())
+           .add_system(play_effects.system());
+    }
+}
+
+fn update_music(
+    mut output: NonSendMut<SoundOutput>,
+    mut music: ResMut<Music>,
+    tunes: Res<Assets<Tune>>,
+    settings: Res<settings::Settings>,
+) {
+    // the volume can be changed from the options while music plays
+    if let Some(sink) = output.music.as_ref() {
+        sink.set_volume(settings.music_volume());
+    }
+
+    if !music.changed {
+        return;
+    }
+
+    if let Some(sink) = output.music.take() {
+        sink.stop();
+    }
+
+    let tune = match music.tune.as_ref() {
+        Some(handle) => match tunes.get(handle) {
+            Some(tune) => tune,
+            None => return,
+        },
+        None => {
+            music.changed = false;
+            return;
+        },
+    };
+
+    if let Some((_, stream_handle)) = output.stream.as_ref() {
+        match Sink::try_new(stream_handle) {
+            Ok(sink) => {
+                sink.set_volume(settings.music_volume());
+                sink.append(tune.source().repeat_infinite());
+                output.music = Some(sink);
+            },
+            Err(e) => println!("Couldn't play music: {}", e),
+        }
+    }
+    music.changed = false;
+}
+
+// effects are picked from what just happened in the game, by their keys in assets/manifest.ron
+fn play_effects(
+    output: NonSend<SoundOutput>,
+    game_assets: Res<asset_loader::GameAssets>,
+    tunes: Res<Assets<Tune>>,
+    settings: Res<settings::Settings>,
+    mut bullet_event_reader: EventReader<bullet::BulletEvent>,
+    mut score_event_reader: EventReader<score::ScoreEvent>,
+    mut player_hit_event_reader: EventReader<player::PlayerHitEvent>,
+) {
+    let mut played = vec!();
+    for event in bullet_event_reader.iter() {
+        if let bullet::Owner::Player(_) = event.owner {
+            played.push("shot");
+        }
+    }
+    for event in score_event_reader.iter() {
+        match event.kind {
+            score::ScoreKind::Kill(_) => played.push("explosion"),
+            score::ScoreKind::Pickup(_) => played.push("pickup"),
+            score::ScoreKind::Graze => (),
+        }
+    }
+    if player_hit_event_reader.iter().next().is_some() {
+        played.push("hit");
+    }
+
+    let stream_handle = match output.stream.as_ref() {
+        Some((_, stream_handle)) => stream_handle,
+        None => return,
+    };
+    // the same effect several times on one frame would only sound louder
+    played.sort_unstable();
+    played.dedup();
+    for name in played {
+        if let Some(tune) = tunes.get(game_assets.sound(name)) {
+            if let Err(e) = stream_handle.play_raw(tune.source().amplify(settings.sfx_volume())) {
+                println!("Couldn't play sound {}: {}", name, e);
+            }
+        }
+    }
+}
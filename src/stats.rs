@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{attract, bullet, campaign, difficulty, enemy, player, replay, save, score};
+
+static GRADING_FILE:&str = "grading.ron";
+static GRADING_VERSION:u32 = 1;
+static STATS_DIR:&str = "stats";
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunStats {
+    pub stage: String,
+    pub difficulty: String,
+    pub cleared: bool,
+    pub game_over: bool,
+    pub shots_fired: u32,
+    pub hits: u32,
+    pub accuracy: f32,
+    pub damage_taken: u32,
+    pub deaths: u32,
+    pub bombs_used: u32,
+    pub max_combo: u32,
+    pub clear_time: f32,
+    pub grade: Option<Grade>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum Grade {
+    S, A, B, C, D,
+}
+
+impl Grade {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Grade::S => "S",
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+        }
+    }
+}
+
+// a run is rated out of 100 from its accuracy, minus penalties, plus a combo bonus,
+// and the rating is compared against these minimums. Designers can override them
+// by editing grading.ron in the data directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GradeThresholds {
+    pub s: f32,
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub damage_penalty: f32,
+    pub death_penalty: f32,
+    pub bomb_penalty: f32,
+    pub combo_bonus: f32,
+    pub max_combo_bonus: f32,
+}
+
+impl Default for GradeThresholds {
+    fn default() -> Self {
+        GradeThresholds {
+            s: 90.0,
+            a: 75.0,
+            b: 55.0,
+            c: 35.0,
+            damage_penalty: 10.0,
+            death_penalty: 25.0,
+            bomb_penalty: 5.0,
+            combo_bonus: 0.5,
+            max_combo_bonus: 20.0,
+        }
+    }
+}
+
+impl GradeThresholds {
+    pub fn load() -> Self {
+        save::load(GRADING_FILE, GRADING_VERSION).unwrap_or_default()
+    }
+
+    pub fn rating(&self, stats: &RunStats) -> f32 {
+        stats.accuracy * 100.0
+        - stats.damage_taken as f32 * self.damage_penalty
+        - stats.deaths as f32 * self.death_penalty
+        - stats.bombs_used as f32 * self.bomb_penalty
+        + (stats.max_combo as f32 * self.combo_bonus).min(self.max_combo_bonus)
+    }
+
+    pub fn grade(&self, stats: &RunStats) -> Grade {
+        let rating = self.rating(stats);
+        if rating >= self.s {
+            Grade::S
+        } else if rating >= self.a {
+            Grade::A
+        } else if rating >= self.b {
+            Grade::B
+        } else if rating >= self.c {
+            Grade::C
+        } else {
+            Grade::D
+        }
+    }
+}
+
+pub struct StatsPlugin;
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<RunStats>()
+           .insert_resource(GradeThresholds::load())
+           .add_system_set(
+               SystemSet::on_enter(crate::AppState::InGame)
+                   .with_system(reset_stats.system())
+           )
+           .add_system_set(
+               SystemSet::on_exit(crate::AppState::InGame)
+                   .with_system(finish_run.system())
+           )
+           // counted in ticks so a replay of the run takes exactly as long
+           .add_system_set_to_stage(replay::FIXED_UPDATE,
+               replay::on_tick()
+                   .with_system(track_clear_time.system())
+           )
+           .add_system_set(
+               SystemSet::on_update(crate::AppState::InGame)
+                   .with_system(track_shots.system())
+                   .with_system(track_hits.system())
+                   .with_system(track_damage.system())
+           );
+    }
+}
+
+fn reset_stats(
+    mut stats: ResMut<RunStats>,
+    campaign: Res<campaign::Campaign>,
+    difficulty: Res<difficulty::Difficulty>,
+) {
+    *stats = RunStats {
+        stage: campaign.current_stage().id.clone(),
+        difficulty: difficulty.level.name().to_string(),
+        ..Default::default()
+    };
+}
+
+fn track_clear_time(
+    mut stats: ResMut<RunStats>,
+) {
+    stats.clear_time += replay::TICK_SECONDS;
+}
+
+fn track_shots(
+    mut stats: ResMut<RunStats>,
+    mut bullet_event_reader: EventReader<bullet::BulletEvent>,
+) {
+    for event in bullet_event_reader.iter() {
+        if let bullet::Owner::Player(_) = event.owner {
+            stats.shots_fired += 1;
+        }
+    }
+}
+
+fn track_hits(
+    mut stats: ResMut<RunStats>,
+    mut enemy_hit_event_reader: EventReader<enemy::EnemyHitEvent>,
+) {
+    stats.hits += enemy_hit_event_reader.iter().count() as u32;
+}
+
+fn track_damage(
+    mut stats: ResMut<RunStats>,
+    mut player_hit_event_reader: EventReader<player::PlayerHitEvent>,
+) {
+    stats.damage_taken += player_hit_event_reader.iter().count() as u32;
+}
+
+fn finish_run(
+    mut stats: ResMut<RunStats>,
+    score: Res<score::Score>,
+    thresholds: Res<GradeThresholds>,
+    attract: Res<attract::AttractMode>,
+    players: Query<&player::PlayerStatus>,
+    mut stage_cleared_event_reader: EventReader<campaign::StageClearedEvent>,
+) {
+    // the clear is sent on the same frame the stage is left
+    stats.cleared = stage_cleared_event_reader.iter().next().is_some();
+    stats.accuracy = if stats.shots_fired > 0 { stats.hits as f32 / stats.shots_fired as f32 } else { 0.0 };
+    stats.max_combo = score.players.values().map(|player_score| player_score.max_combo).max().unwrap_or(0);
+    stats.bombs_used = players.iter()
+                              .map(|status| player::STARTING_BOMBS.saturating_sub(status.bombs))
+                              .sum();
+    stats.grade = Some(thresholds.grade(&stats));
+
+    // a demo isn't anybody's run
+    if attract.active {
+        return;
+    }
+
+    // quitting or restarting isn't a finished run, so only clears and game overs are exported
+    if !stats.cleared && !stats.game_over {
+        return;
+    }
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    save::export_json(STATS_DIR, &format!("{}-{}.json", stats.stage, timestamp), &*stats);
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::{hud, game_controller::{GameButton, MenuInput}};
+
+// everything spawned for a full-screen menu or message, removed when its state exits
+pub struct Screen;
+pub struct ScreenText;
+
+pub fn spawn_screen_text(
+    commands: &mut Commands,
+    ui_font: &hud::UiFont,
+    font_size: f32,
+) {
+    commands.spawn_bundle(TextBundle {
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    margin: Rect::all(Val::Auto),
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: ui_font.0.clone(),
+                        font_size,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        horizontal: HorizontalAlign::Center,
+                        ..Default::default()
+                    },
+                ),
+                ..Default::default()
+            })
+            .insert(Screen)
+            .insert(ScreenText);
+}
+
+pub fn set_screen_text(
+    texts: &mut Query<&mut Text, With<ScreenText>>,
+    value: &str,
+) {
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.to_string();
+        }
+    }
+}
+
+pub fn cleanup_screen(
+    mut commands: Commands,
+    screens: Query<Entity, With<Screen>>,
+) {
+    for entity in screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// moves a menu cursor up and down a list, wrapping at either end
+pub fn navigate_menu(
+    menu_input: &MenuInput,
+    selected: usize,
+    count: usize,
+) -> usize {
+    if menu_input.pressed(GameButton::Up) {
+        (selected + count - 1) % count
+    } else if menu_input.pressed(GameButton::Down) {
+        (selected + 1) % count
+    } else {
+        selected
+    }
+}
+
+// -1 for left, 1 for right, 0 otherwise
+pub fn menu_step(menu_input: &MenuInput) -> i32 {
+    if menu_input.pressed(GameButton::Left) {
+        -1
+    } else if menu_input.pressed(GameButton::Right) {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn menu_text(
+    title: &str,
+    items: &[&str],
+    selected: usize,
+) -> String {
+    let mut value = format!("{}\n\n", title);
+    for (i, item) in items.iter().enumerate() {
+        if i == selected {
+            value.push_str(&format!("> {} <\n", item));
+        } else {
+            value.push_str(&format!("{}\n", item));
+        }
+    }
+
+    value
+}